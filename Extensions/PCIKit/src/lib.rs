@@ -7,7 +7,7 @@
 use num_enum::IntoPrimitive;
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "ext")]
-use skykit::msg::Message;
+use skykit::{msg::Message, syscall::SystemCallError};
 
 #[macro_use]
 extern crate bitfield_struct;
@@ -94,8 +94,8 @@ pub enum PCIRequest {
 
 #[cfg(feature = "ext")]
 impl PCIRequest {
-    pub unsafe fn send(self, pid: u64) {
-        Message::new(pid, postcard::to_allocvec(&self).unwrap().leak()).send();
+    pub unsafe fn send(self, pid: u64) -> Result<(), SystemCallError> {
+        Message::send(pid, &postcard::to_allocvec(&self).unwrap())
     }
 }
 
@@ -122,31 +122,43 @@ impl PCIDevice {
 
     #[must_use]
     pub unsafe fn cfg_read8<A: Into<u8>, R: From<u8>>(&self, off: A) -> R {
-        PCIRequest::Read8(self.addr, off.into()).send(self.pid);
+        PCIRequest::Read8(self.addr, off.into())
+            .send(self.pid)
+            .unwrap();
         Message::recv().data[0].into()
     }
 
     #[must_use]
     pub unsafe fn cfg_read16<A: Into<u8>, R: From<u16>>(&self, off: A) -> R {
-        PCIRequest::Read16(self.addr, off.into()).send(self.pid);
+        PCIRequest::Read16(self.addr, off.into())
+            .send(self.pid)
+            .unwrap();
         u16::from_le_bytes(Message::recv().data.try_into().unwrap()).into()
     }
 
     #[must_use]
     pub unsafe fn cfg_read32<A: Into<u8>, R: From<u32>>(&self, off: A) -> R {
-        PCIRequest::Read32(self.addr, off.into()).send(self.pid);
+        PCIRequest::Read32(self.addr, off.into())
+            .send(self.pid)
+            .unwrap();
         u32::from_le_bytes(Message::recv().data.try_into().unwrap()).into()
     }
 
     pub unsafe fn cfg_write8<A: Into<u8>, R: Into<u8>>(&self, off: A, value: R) {
-        PCIRequest::Write8(self.addr, off.into(), value.into()).send(self.pid);
+        PCIRequest::Write8(self.addr, off.into(), value.into())
+            .send(self.pid)
+            .unwrap();
     }
 
     pub unsafe fn cfg_write16<A: Into<u8>, R: Into<u16>>(&self, off: A, value: R) {
-        PCIRequest::Write16(self.addr, off.into(), value.into()).send(self.pid);
+        PCIRequest::Write16(self.addr, off.into(), value.into())
+            .send(self.pid)
+            .unwrap();
     }
 
     pub unsafe fn cfg_write32<A: Into<u8>, R: Into<u32>>(&self, off: A, value: R) {
-        PCIRequest::Write32(self.addr, off.into(), value.into()).send(self.pid);
+        PCIRequest::Write32(self.addr, off.into(), value.into())
+            .send(self.pid)
+            .unwrap();
     }
 }
@@ -143,11 +143,11 @@ extern "C" fn _start(instance: OSDTEntry) -> ! {
                 ("Function".into(), func.into()),
             ]);
 
-            let ent = instance.new_child(None);
-            ent.set_property("VendorID", vendor_id.into());
-            ent.set_property("DeviceID", device_id.into());
-            ent.set_property("ClassCode", class_code.into());
-            ent.set_property("Address", addr.into());
+            let ent = instance.new_child(None).unwrap();
+            ent.set_property("VendorID", vendor_id.into()).unwrap();
+            ent.set_property("DeviceID", device_id.into()).unwrap();
+            ent.set_property("ClassCode", class_code.into()).unwrap();
+            ent.set_property("Address", addr.into()).unwrap();
 
             if !multifunction {
                 break;
@@ -181,8 +181,7 @@ extern "C" fn _start(instance: OSDTEntry) -> ! {
                 continue;
             }
         };
-        unsafe {
-            Message::new(msg.pid, data.leak()).send();
-        }
+        // The requester may have quit in the meantime, nothing to do then.
+        let _ = unsafe { Message::send(msg.pid, &data) };
     }
 }
@@ -0,0 +1,60 @@
+// Copyright (c) ChefKiss 2021-2025. Licensed under the Thou Shalt Not Profit License version 1.5. See LICENSE for details.
+
+#![no_std]
+#![no_main]
+#![deny(warnings, clippy::nursery, unused_extern_crates)]
+
+use core::{arch::x86_64::_rdtsc, fmt::Write};
+
+use skykit::{osdtentry::OSDTEntry, syscall::SystemCall, userspace::logger::KWriter};
+
+const ROUNDS: u64 = 16;
+const ITERATIONS: u64 = 10_000;
+// Not a valid call number, the kernel rejects it right after decoding so only the round trip is measured.
+const NOP_CALL: u64 = u64::MAX;
+
+unsafe fn via_syscall() {
+    core::arch::asm!(
+        "syscall",
+        inout("rdi") NOP_CALL => _,
+        lateout("rax") _,
+        lateout("rcx") _,
+        lateout("r11") _,
+        options(nostack),
+    );
+}
+
+unsafe fn via_int() {
+    core::arch::asm!(
+        "int 249",
+        inout("rdi") NOP_CALL => _,
+        lateout("rax") _,
+        options(nostack),
+    );
+}
+
+fn measure(f: unsafe fn()) -> u64 {
+    (0..ROUNDS)
+        .map(|_| unsafe {
+            let start = _rdtsc();
+            for _ in 0..ITERATIONS {
+                f();
+            }
+            (_rdtsc() - start) / ITERATIONS
+        })
+        .min()
+        .unwrap()
+}
+
+#[no_mangle]
+extern "C" fn _start(_instance: OSDTEntry) -> ! {
+    let syscall = measure(via_syscall);
+    let int = measure(via_int);
+    writeln!(
+        KWriter,
+        "SKBench: SYSCALL round trip takes {syscall} cycles"
+    )
+    .unwrap();
+    writeln!(KWriter, "SKBench: INT 249 round trip takes {int} cycles").unwrap();
+    unsafe { SystemCall::quit() }
+}
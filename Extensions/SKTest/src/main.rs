@@ -20,9 +20,14 @@ use skykit::{
     msg::Message,
     osdtentry::{OSDTEntry, OSDTENTRY_NAME_KEY},
     osvalue::OSValue,
-    syscall::SystemCall,
+    syscall::{raw, MapFlags, SystemCall, SystemCallError},
     userspace::{logger::KWriter, port::Port},
 };
+// Links SkyKit from its shared object rather than statically.
+use skykit_dylib as _;
+
+/// Payload of the "SmallMessage" command, checked by whichever process receives it.
+const SMALL_MESSAGE: [u8; 4] = 0xDEAD_BEEF_u32.to_be_bytes();
 
 #[derive(IntoPrimitive)]
 #[repr(u8)]
@@ -105,7 +110,7 @@ impl PS2Ctl {
                 .with_port2_intr(false)
                 .with_port1_translation(true)
         };
-        unsafe { SystemCall::register_irq_handler(1) }
+        unsafe { SystemCall::register_irq_handler(1).unwrap() }
         self.send_cmd(PS2CtlCmd::WriteControllerCfg, false);
         unsafe { self.data_port.write(cfg.into()) }
         while self.input_full() {}
@@ -116,7 +121,7 @@ fn print_ent(ent: OSDTEntry, ident: usize) {
     let spacing = " ".repeat(ident);
 
     let id: u64 = ent.into();
-    let props = ent.properties();
+    let props = ent.properties().unwrap();
     writeln!(
         KWriter,
         "{spacing}+ {} <{}>",
@@ -130,10 +135,10 @@ fn print_ent(ent: OSDTEntry, ident: usize) {
     .unwrap();
 
     for (k, v) in props.into_iter().filter(|(k, _)| k != OSDTENTRY_NAME_KEY) {
-        writeln!(KWriter, "{spacing}|- {k}: {v:X?}").unwrap();
+        writeln!(KWriter, "{spacing}|- {k}: {v}").unwrap();
     }
 
-    for child in ent.children() {
+    for child in ent.children().unwrap() {
         print_ent(child, ident + 2);
     }
 }
@@ -151,6 +156,13 @@ extern "C" fn _start(_instance: OSDTEntry) -> ! {
     loop {
         let msg = unsafe { Message::recv() };
         if msg.pid != 0 {
+            if msg.data.len() == SMALL_MESSAGE.len() {
+                if msg.data == SMALL_MESSAGE {
+                    writeln!(KWriter, "Received a 4 byte message intact").unwrap();
+                } else {
+                    writeln!(KWriter, "Received a corrupted message: {:X?}", msg.data).unwrap();
+                }
+            }
             continue;
         }
 
@@ -225,19 +237,14 @@ extern "C" fn _start(_instance: OSDTEntry) -> ! {
             } else {
                 match s.as_str() {
                     "OSDeviceTree" => print_ent(OSDTEntry::default(), 0),
+                    "OSDTSnapshot" => unsafe { SystemCall::osdt_snapshot() },
                     "Edit" => {
                         ed_mode = true;
                         s.clear();
                         continue;
                     }
                     "AccessInvalid" => unsafe {
-                        core::arch::asm!(
-                            "int 249",
-                            in("rdi") SystemCall::KPrint as u64,
-                            in("rsi") 0u64,
-                            in("rdx") 0u64,
-                            options(nostack),
-                        );
+                        let _ = raw::kprint(0, 0);
                         panic!("The kernel did not terminate the process...");
                     },
                     v if v.split_whitespace().next() == Some("Message") => 'a: {
@@ -250,10 +257,37 @@ extern "C" fn _start(_instance: OSDTEntry) -> ! {
                             writeln!(KWriter, "Expected data").unwrap();
                             break 'a;
                         };
-                        unsafe {
-                            Message::new(pid, data.to_be_bytes().to_vec().leak()).send();
+                        if let Err(e) = unsafe { Message::send(pid, &data.to_be_bytes()) } {
+                            writeln!(KWriter, "Failed to send message: {e:?}").unwrap();
                         }
                     }
+                    // Payloads smaller than a page come from the heap arenas, which must not be
+                    // handed over to the target. Send it to this process to check what arrives.
+                    v if v.split_whitespace().next() == Some("SmallMessage") => 'a: {
+                        let Some(pid) = v.split_whitespace().nth(1).and_then(|v| v.parse().ok())
+                        else {
+                            writeln!(KWriter, "Expected PID").unwrap();
+                            break 'a;
+                        };
+                        if let Err(e) = unsafe { Message::send(pid, &SMALL_MESSAGE) } {
+                            writeln!(KWriter, "Failed to send message: {e:?}").unwrap();
+                        }
+                    }
+                    // The limit is below the space reserved for the stack, which must not count.
+                    "MemoryLimit" => unsafe {
+                        let flags = MapFlags::READ | MapFlags::WRITE;
+                        match SystemCall::map(0, 0x40_0000, flags) {
+                            Err(SystemCallError::LimitExceeded) => {}
+                            v => writeln!(KWriter, "Expected the limit to be hit: {v:?}").unwrap(),
+                        }
+                        match SystemCall::map(0, 0x1_0000, flags) {
+                            Ok(addr) => {
+                                SystemCall::unmap(addr, 0x1_0000).unwrap();
+                                writeln!(KWriter, "Memory limit enforced").unwrap();
+                            }
+                            Err(e) => writeln!(KWriter, "Failed to allocate: {e:?}").unwrap(),
+                        }
+                    },
                     _ => writeln!(KWriter, "{s}").unwrap(),
                 }
                 s.clear();
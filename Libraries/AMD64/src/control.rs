@@ -0,0 +1,47 @@
+// Copyright (c) ChefKiss 2021-2025. Licensed under the Thou Shalt Not Profit License version 1.5. See LICENSE for details.
+
+#[bitfield(u64)]
+pub struct ControlReg4 {
+    pub vme: bool,
+    pub pvi: bool,
+    pub tsd: bool,
+    pub de: bool,
+    pub pse: bool,
+    pub pae: bool,
+    pub mce: bool,
+    pub pge: bool,
+    pub pce: bool,
+    pub osfxsr: bool,
+    pub osxmmexcpt: bool,
+    pub umip: bool,
+    pub la57: bool,
+    pub vmxe: bool,
+    pub smxe: bool,
+    __: bool,
+    pub fsgsbase: bool,
+    pub pcide: bool,
+    pub osxsave: bool,
+    __: bool,
+    pub smep: bool,
+    pub smap: bool,
+    pub pke: bool,
+    pub cet: bool,
+    pub pks: bool,
+    #[bits(39)]
+    __: u64,
+}
+
+impl ControlReg4 {
+    #[inline]
+    #[must_use]
+    pub unsafe fn read() -> Self {
+        let value: u64;
+        core::arch::asm!("mov {}, cr4", out(reg) value, options(nomem, nostack, preserves_flags));
+        Self::from(value)
+    }
+
+    #[inline]
+    pub unsafe fn write(self) {
+        core::arch::asm!("mov cr4, {}", in(reg) u64::from(self), options(nostack, preserves_flags));
+    }
+}
@@ -71,12 +71,126 @@ pub struct CPUFeatures {
     __: u8,
 }
 
+impl CPUFeatures {
+    #[must_use]
+    pub const fn as_named(&self) -> [(&'static str, bool); 40] {
+        [
+            ("sse3", self.sse3()),
+            ("pclmulqdq", self.pclmulqdq()),
+            ("monitor", self.monitor()),
+            ("ssse3", self.ssse3()),
+            ("fma", self.fma()),
+            ("cmpxchg16b", self.cmpxchg16b()),
+            ("sse41", self.sse41()),
+            ("sse42", self.sse42()),
+            ("movbe", self.movbe()),
+            ("popcnt", self.popcnt()),
+            ("aes", self.aes()),
+            ("xsave", self.xsave()),
+            ("osxsave", self.osxsave()),
+            ("avx", self.avx()),
+            ("f16c", self.f16c()),
+            ("rdrand", self.rdrand()),
+            ("is_guest", self.is_guest()),
+            ("fpu", self.fpu()),
+            ("vme", self.vme()),
+            ("de", self.de()),
+            ("pse", self.pse()),
+            ("tsc", self.tsc()),
+            ("msr", self.msr()),
+            ("pae", self.pae()),
+            ("mce", self.mce()),
+            ("cmpxchg8b", self.cmpxchg8b()),
+            ("apic", self.apic()),
+            ("sysenter_sysexit", self.sysenter_sysexit()),
+            ("mtrr", self.mtrr()),
+            ("pge", self.pge()),
+            ("mca", self.mca()),
+            ("cmov", self.cmov()),
+            ("pat", self.pat()),
+            ("pse36", self.pse36()),
+            ("clfsh", self.clfsh()),
+            ("mmx", self.mmx()),
+            ("fxsr", self.fxsr()),
+            ("sse", self.sse()),
+            ("sse2", self.sse2()),
+            ("htt", self.htt()),
+        ]
+    }
+}
+
+#[bitfield(u64)]
+pub struct CPUStructuredExtFeatures {
+    // EBX
+    pub fsgsbase: bool,
+    #[bits(6)]
+    __: u8,
+    pub smep: bool,
+    #[bits(12)]
+    __: u16,
+    pub smap: bool,
+    #[bits(11)]
+    __: u16,
+    // ECX
+    #[bits(2)]
+    __: u8,
+    pub umip: bool,
+    #[bits(29)]
+    __: u32,
+}
+
+impl CPUStructuredExtFeatures {
+    #[must_use]
+    pub const fn as_named(&self) -> [(&'static str, bool); 4] {
+        [
+            ("fsgsbase", self.fsgsbase()),
+            ("smep", self.smep()),
+            ("smap", self.smap()),
+            ("umip", self.umip()),
+        ]
+    }
+}
+
+#[bitfield(u32)]
+pub struct CPUExtFeatures {
+    // EDX
+    #[bits(11)]
+    __: u16,
+    pub syscall_sysret: bool,
+    #[bits(8)]
+    __: u8,
+    pub nx: bool,
+    #[bits(5)]
+    __: u8,
+    pub page_1gb: bool,
+    pub rdtscp: bool,
+    __: bool,
+    pub long_mode: bool,
+    #[bits(2)]
+    __: u8,
+}
+
+impl CPUExtFeatures {
+    #[must_use]
+    pub const fn as_named(&self) -> [(&'static str, bool); 5] {
+        [
+            ("syscall_sysret", self.syscall_sysret()),
+            ("nx", self.nx()),
+            ("page_1gb", self.page_1gb()),
+            ("rdtscp", self.rdtscp()),
+            ("long_mode", self.long_mode()),
+        ]
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct CPUIdentification {
     pub largest_func_id: u32,
     pub vendor_string: ArrayString<12>,
     pub features: CPUFeatures,
     pub misc: FeaturesMisc,
+    pub structured_ext_features: CPUStructuredExtFeatures,
+    pub ext_features: CPUExtFeatures,
 }
 
 impl Default for CPUIdentification {
@@ -103,11 +217,29 @@ impl CPUIdentification {
         let features = CPUFeatures::from(u64::from(res.ecx) | (u64::from(res.edx) << 32));
         let misc = FeaturesMisc::from(res.ebx);
 
+        // Function 7, sub-leaf 0
+        let structured_ext_features = if largest_func_id >= 7 {
+            let res = unsafe { core::arch::x86_64::__cpuid_count(7, 0) };
+            CPUStructuredExtFeatures::from(u64::from(res.ebx) | (u64::from(res.ecx) << 32))
+        } else {
+            CPUStructuredExtFeatures::new()
+        };
+
+        // Extended function 0x8000_0001
+        let largest_ext_func_id = unsafe { core::arch::x86_64::__cpuid(0x8000_0000) }.eax;
+        let ext_features = if largest_ext_func_id >= 0x8000_0001 {
+            CPUExtFeatures::from(unsafe { core::arch::x86_64::__cpuid(0x8000_0001) }.edx)
+        } else {
+            CPUExtFeatures::new()
+        };
+
         Self {
             largest_func_id,
             vendor_string,
             features,
             misc,
+            structured_ext_features,
+            ext_features,
         }
     }
 }
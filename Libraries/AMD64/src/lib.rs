@@ -4,6 +4,7 @@
 #![deny(warnings, clippy::nursery, unused_extern_crates)]
 #![allow(clippy::missing_safety_doc)]
 
+pub mod control;
 pub mod cpuid;
 pub mod io;
 pub mod msr;
@@ -0,0 +1,19 @@
+// Copyright (c) ChefKiss 2021-2025. Licensed under the Thou Shalt Not Profit License version 1.5. See LICENSE for details.
+
+#[bitfield(u64)]
+pub struct GSBase {
+    pub base: u64,
+}
+
+impl super::ModelSpecificReg for GSBase {
+    const MSR_NUM: u32 = 0xC000_0101;
+}
+
+#[bitfield(u64)]
+pub struct KernelGSBase {
+    pub base: u64,
+}
+
+impl super::ModelSpecificReg for KernelGSBase {
+    const MSR_NUM: u32 = 0xC000_0102;
+}
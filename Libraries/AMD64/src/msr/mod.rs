@@ -2,7 +2,9 @@
 
 pub mod apic;
 pub mod efer;
+pub mod gs_base;
 pub mod pat;
+pub mod syscall;
 pub mod vm_cr;
 
 pub trait ModelSpecificReg: Sized + From<u64> {
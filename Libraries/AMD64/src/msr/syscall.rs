@@ -0,0 +1,31 @@
+// Copyright (c) ChefKiss 2021-2025. Licensed under the Thou Shalt Not Profit License version 1.5. See LICENSE for details.
+
+#[bitfield(u64)]
+pub struct SystemCallTargetAddrReg {
+    pub legacy_target: u32,
+    pub syscall_cs_ss: u16,
+    pub sysret_cs_ss: u16,
+}
+
+impl super::ModelSpecificReg for SystemCallTargetAddrReg {
+    const MSR_NUM: u32 = 0xC000_0081;
+}
+
+#[bitfield(u64)]
+pub struct LongModeSystemCallTargetAddrReg {
+    pub target: u64,
+}
+
+impl super::ModelSpecificReg for LongModeSystemCallTargetAddrReg {
+    const MSR_NUM: u32 = 0xC000_0082;
+}
+
+#[bitfield(u64)]
+pub struct SystemCallFlagMaskReg {
+    pub mask: u32,
+    __: u32,
+}
+
+impl super::ModelSpecificReg for SystemCallFlagMaskReg {
+    const MSR_NUM: u32 = 0xC000_0084;
+}
@@ -6,6 +6,41 @@ pub const PAGE_MASK: u64 = 0xFFF;
 pub const PHYS_VIRT_OFFSET: u64 = 0xFFFF_8000_0000_0000;
 pub const KERNEL_VIRT_OFFSET: u64 = 0xFFFF_FFFF_8000_0000;
 
+/// Sizes of the pages a single entry can map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PageSize {
+    Size4KiB,
+    /// Mapped by a page directory entry.
+    Size2MiB,
+    /// Mapped by a page directory pointer table entry, if supported.
+    Size1GiB,
+}
+
+impl PageSize {
+    #[inline]
+    #[must_use]
+    pub const fn size(self) -> u64 {
+        match self {
+            Self::Size4KiB => PAGE_SIZE,
+            Self::Size2MiB => 0x20_0000,
+            Self::Size1GiB => 0x4000_0000,
+        }
+    }
+
+    /// The largest size the processor supports, 2 MiB pages always being available in long mode.
+    #[must_use]
+    pub fn largest_supported() -> Self {
+        if crate::cpuid::CPUIdentification::new()
+            .ext_features
+            .page_1gb()
+        {
+            Self::Size1GiB
+        } else {
+            Self::Size2MiB
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct PageTableIndices {
     pub pml4: usize,
@@ -39,8 +74,9 @@ pub struct PageTableEntry {
     pub dirty: bool,
     pub huge_or_pat: bool,
     pub global: bool,
-    #[bits(2)]
-    __: u8,
+    /// Ignored by the processor, marks a read-only mapping of a shared frame to copy on write.
+    pub copy_on_write: bool,
+    __: bool,
     pub pat: bool,
     #[bits(40)]
     pub address: u64,
@@ -49,19 +85,151 @@ pub struct PageTableEntry {
     pub no_execute: bool,
 }
 
+impl PageTableEntry {
+    /// Whether the entry maps a 2 MiB or 1 GiB page instead of pointing to a table.
+    ///
+    /// Only meaningful for page directory and page directory pointer table entries.
+    #[inline]
+    #[must_use]
+    pub const fn is_huge(&self) -> bool {
+        self.present() && self.huge_or_pat()
+    }
+
+    /// Address of the page mapped by a huge entry, whose lowest address bit is the PAT bit.
+    #[inline]
+    #[must_use]
+    pub const fn huge_address(&self) -> u64 {
+        (self.address() & !1) << 12
+    }
+}
+
+/// A page mapped by a leaf entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mapping {
+    pub virt: u64,
+    pub phys: u64,
+    pub flags: PageTableFlags,
+    pub size: PageSize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnmapError {
+    /// The range only covers part of the huge page at the address.
+    PartialHugePage(u64),
+}
+
+/// Translations to invalidate after changing page tables, flushing the whole TLB instead once
+/// more than [`Self::CAPACITY`] addresses are added.
+#[derive(Debug, Clone)]
+pub struct TlbFlush {
+    addrs: [u64; Self::CAPACITY],
+    len: usize,
+    full: bool,
+}
+
+impl TlbFlush {
+    pub const CAPACITY: usize = 32;
+
+    #[inline]
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            addrs: [0; Self::CAPACITY],
+            len: 0,
+            full: false,
+        }
+    }
+
+    #[inline]
+    pub const fn add(&mut self, virt: u64) {
+        if self.len == Self::CAPACITY {
+            self.full = true;
+        } else {
+            self.addrs[self.len] = virt;
+            self.len += 1;
+        }
+    }
+
+    /// The addresses to invalidate, or `None` if the whole TLB must be flushed.
+    #[inline]
+    #[must_use]
+    pub fn addresses(&self) -> Option<&[u64]> {
+        (!self.full).then(|| &self.addrs[..self.len])
+    }
+
+    pub unsafe fn flush(&self) {
+        let Some(addrs) = self.addresses() else {
+            core::arch::asm!(
+                "mov {0}, cr3",
+                "mov cr3, {0}",
+                out(reg) _,
+                options(nostack, preserves_flags),
+            );
+            return;
+        };
+        for &virt in addrs {
+            core::arch::asm!("invlpg [{}]", in(reg) virt, options(nostack, preserves_flags));
+        }
+    }
+}
+
+impl Default for TlbFlush {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Moves addresses in the non-canonical hole between the two halves to the start of the higher
+/// half.
+#[inline]
+const fn canonical(virt: u64) -> u64 {
+    if virt >= 0x8000_0000_0000 && virt < PHYS_VIRT_OFFSET {
+        PHYS_VIRT_OFFSET
+    } else {
+        virt
+    }
+}
+
+/// Iterator over the pages mapped within a range, see [`PageTable::mappings`].
+#[derive(Debug)]
+pub struct Mappings<'a, const VIRT_OFF: u64> {
+    table: &'a PageTable<VIRT_OFF>,
+    cursor: Option<u64>,
+    last: u64,
+}
+
+impl<const VIRT_OFF: u64> Iterator for Mappings<'_, VIRT_OFF> {
+    type Item = Mapping;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let virt = self.cursor.filter(|&v| v <= self.last)?;
+            let (mapping, span) = unsafe { self.table.lookup(virt) };
+            self.cursor = (virt & !(span - 1)).checked_add(span).map(canonical);
+            if mapping.is_some() {
+                return mapping;
+            }
+        }
+    }
+}
+
 #[repr(C, align(4096))]
 #[derive(Debug)]
 pub struct PageTable<const VIRT_OFF: u64> {
     pub entries: [PageTableEntry; 512],
 }
 
-type AllocEntryFn<'a> = &'a dyn Fn() -> u64;
+/// Returns the physical address of a zeroed frame for a new table.
+type AllocEntryFn<'a, E> = &'a dyn Fn() -> Result<u64, E>;
+type FreeTableFn<'a> = &'a dyn Fn(u64);
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct PageTableFlags {
     pub present: bool,
     pub writable: bool,
     pub user: bool,
+    pub executable: bool,
+    pub copy_on_write: bool,
     pub pat_index: u8,
 }
 
@@ -73,6 +241,8 @@ impl PageTableFlags {
             present: false,
             writable: false,
             user: false,
+            executable: true,
+            copy_on_write: false,
             pat_index: 0,
         }
     }
@@ -98,6 +268,20 @@ impl PageTableFlags {
         self
     }
 
+    #[inline]
+    #[must_use]
+    pub const fn with_executable(mut self, executable: bool) -> Self {
+        self.executable = executable;
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn with_copy_on_write(mut self, copy_on_write: bool) -> Self {
+        self.copy_on_write = copy_on_write;
+        self
+    }
+
     #[inline]
     #[must_use]
     pub const fn with_pat_entry(mut self, pat_entry: u8) -> Self {
@@ -124,6 +308,24 @@ impl PageTableFlags {
             .with_pcd((self.pat_index & 0b010) != 0)
             .with_huge_or_pat(pte && pat)
             .with_pat(!pte && pat)
+            .with_no_execute(!self.executable)
+            .with_copy_on_write(pte && self.copy_on_write)
+    }
+
+    /// Entry mapping a 2 MiB or 1 GiB page at `phys`.
+    #[inline]
+    #[must_use]
+    pub const fn as_huge_entry(self, phys: u64) -> PageTableEntry {
+        PageTableEntry::new()
+            .with_present(self.present)
+            .with_writable(self.writable)
+            .with_user(self.user)
+            .with_pwt((self.pat_index & 0b001) != 0)
+            .with_pcd((self.pat_index & 0b010) != 0)
+            .with_huge_or_pat(true)
+            .with_no_execute(!self.executable)
+            .with_copy_on_write(self.copy_on_write)
+            .with_address((phys >> 12) | ((self.pat_index >> 2) as u64))
     }
 
     #[inline]
@@ -132,6 +334,7 @@ impl PageTableFlags {
         entry.set_present(entry.present() || self.present);
         entry.set_writable(entry.writable() || self.writable);
         entry.set_user(entry.user() || self.user);
+        entry.set_no_execute(entry.no_execute() && !self.executable);
         entry.set_pwt((self.pat_index & 0b001) != 0);
         entry.set_pcd((self.pat_index & 0b010) != 0);
         entry.set_huge_or_pat(pte && pat);
@@ -145,6 +348,8 @@ impl PageTableFlags {
             .with_present(entry.present())
             .with_writable(entry.writable())
             .with_user(entry.user())
+            .with_executable(!entry.no_execute())
+            .with_copy_on_write(pte && entry.copy_on_write())
             .with_pat_entry(
                 (entry.pwt() as u8)
                     | ((entry.pcd() as u8) << 1)
@@ -153,6 +358,24 @@ impl PageTableFlags {
     }
 }
 
+impl PageTableFlags {
+    #[inline]
+    #[must_use]
+    pub const fn from_huge_entry(entry: &PageTableEntry) -> Self {
+        Self::new()
+            .with_present(entry.present())
+            .with_writable(entry.writable())
+            .with_user(entry.user())
+            .with_executable(!entry.no_execute())
+            .with_copy_on_write(entry.copy_on_write())
+            .with_pat_entry(
+                (entry.pwt() as u8)
+                    | ((entry.pcd() as u8) << 1)
+                    | (((entry.address() & 1) as u8) << 2),
+            )
+    }
+}
+
 impl Default for PageTableFlags {
     fn default() -> Self {
         Self::new()
@@ -172,33 +395,66 @@ impl<const VIRT_OFF: u64> PageTable<VIRT_OFF> {
     unsafe fn get(&mut self, offset: usize) -> Option<&mut Self> {
         let entry = &self.entries[offset];
 
-        if entry.present() {
+        if entry.present() && !entry.is_huge() {
             return Some(&mut *(((entry.address() << 12) + VIRT_OFF) as *mut Self));
         }
 
         None
     }
 
+    /// Replaces the huge page mapped by `entry` with a table of pages of `size` mapping the same
+    /// memory.
+    unsafe fn split<E>(
+        entry: &mut PageTableEntry,
+        alloc_entry: AllocEntryFn<E>,
+        size: PageSize,
+    ) -> Result<(), E> {
+        let flags = PageTableFlags::from_huge_entry(entry);
+        let phys = entry.huge_address();
+        let table_phys = alloc_entry()?;
+        let table = &mut *((table_phys + VIRT_OFF) as *mut Self);
+        for (i, child) in table.entries.iter_mut().enumerate() {
+            let phys = phys + i as u64 * size.size();
+            *child = if size == PageSize::Size4KiB {
+                flags.as_entry(true).with_address(phys >> 12)
+            } else {
+                flags.as_huge_entry(phys)
+            };
+        }
+        *entry = flags
+            .with_executable(true)
+            .with_copy_on_write(false)
+            .with_pat_entry(0)
+            .as_entry(false)
+            .with_address(table_phys >> 12);
+        Ok(())
+    }
+
+    /// Returns the table `offset` points to, splitting a huge page it maps into pages of `size`.
     #[inline]
-    #[must_use]
-    unsafe fn get_and_update_or_alloc(
+    unsafe fn get_and_update_or_alloc<E>(
         &mut self,
-        alloc_entry: AllocEntryFn,
+        alloc_entry: AllocEntryFn<E>,
         offset: usize,
         flags: PageTableFlags,
-    ) -> &mut Self {
+        size: PageSize,
+    ) -> Result<&mut Self, E> {
         let entry = &mut self.entries[offset];
 
+        if entry.is_huge() {
+            Self::split(entry, alloc_entry, size)?;
+        }
         if entry.present() {
             flags.update_entry(entry, false);
         } else {
             *entry = flags
+                .with_executable(true)
                 .as_entry(false)
                 .with_present(true)
-                .with_address(alloc_entry() >> 12)
+                .with_address(alloc_entry()? >> 12)
         };
 
-        &mut *(((entry.address() << 12) + VIRT_OFF) as *mut Self)
+        Ok(&mut *(((entry.address() << 12) + VIRT_OFF) as *mut Self))
     }
 
     #[inline]
@@ -214,73 +470,299 @@ impl<const VIRT_OFF: u64> PageTable<VIRT_OFF> {
         &mut *((pml4 + VIRT_OFF) as *mut Self)
     }
 
-    #[inline]
-    pub unsafe fn virt_to_phys(&mut self, virt: u64) -> Option<(u64, PageTableFlags)> {
+    /// The entry mapping `virt` and the size of the page it maps, if any.
+    unsafe fn leaf(&mut self, virt: u64) -> Option<(&mut PageTableEntry, PageSize)> {
         let offs = PageTableIndices::new(virt);
         let pdp = self.get(offs.pml4)?;
+        if pdp.entries[offs.pdp].is_huge() {
+            return Some((&mut pdp.entries[offs.pdp], PageSize::Size1GiB));
+        }
         let pd = pdp.get(offs.pdp)?;
+        if pd.entries[offs.pd].is_huge() {
+            return Some((&mut pd.entries[offs.pd], PageSize::Size2MiB));
+        }
         let pt = pd.get(offs.pd)?;
+        Some((&mut pt.entries[offs.pt], PageSize::Size4KiB)).filter(|(v, _)| v.present())
+    }
 
-        let ent = &pt.entries[offs.pt];
-        if ent.present() {
-            return Some((
-                (ent.address() << 12) | (virt & PAGE_MASK),
-                PageTableFlags::from_entry(ent, true),
-            ));
+    /// The mapping covering `virt`, along with the span of the entry that was last looked at.
+    unsafe fn lookup(&self, virt: u64) -> (Option<Mapping>, u64) {
+        let mut table = self;
+        for level in (1..=4).rev() {
+            let shift = 3 + 9 * level;
+            let span = 1 << shift;
+            let entry = &table.entries[((virt >> shift) & 0x1FF) as usize];
+            if !entry.present() {
+                return (None, span);
+            }
+            if level == 1 || entry.huge_or_pat() {
+                let (phys, flags, size) = match level {
+                    1 => (
+                        entry.address() << 12,
+                        PageTableFlags::from_entry(entry, true),
+                        PageSize::Size4KiB,
+                    ),
+                    2 => (
+                        entry.huge_address(),
+                        PageTableFlags::from_huge_entry(entry),
+                        PageSize::Size2MiB,
+                    ),
+                    _ => (
+                        entry.huge_address(),
+                        PageTableFlags::from_huge_entry(entry),
+                        PageSize::Size1GiB,
+                    ),
+                };
+                let mapping = Mapping {
+                    virt: virt & !(span - 1),
+                    phys,
+                    flags,
+                    size,
+                };
+                return (Some(mapping), span);
+            }
+            table = &*(((entry.address() << 12) + VIRT_OFF) as *const Self);
         }
+        unreachable!()
+    }
 
-        None
+    /// Iterates over the pages mapped within `len` bytes at `virt`, including the huge pages
+    /// that only partially overlap it.
+    #[inline]
+    #[must_use]
+    pub const unsafe fn mappings(&self, virt: u64, len: u64) -> Mappings<'_, VIRT_OFF> {
+        Mappings {
+            table: self,
+            cursor: if len == 0 {
+                None
+            } else {
+                Some(canonical(virt))
+            },
+            last: virt.wrapping_add(len).wrapping_sub(1),
+        }
     }
 
     #[inline]
-    pub unsafe fn map(
+    pub unsafe fn translate(&mut self, virt: u64) -> Option<(u64, PageTableFlags, PageSize)> {
+        let (ent, size) = self.leaf(virt)?;
+        let offset = virt & (size.size() - 1);
+        Some(if size == PageSize::Size4KiB {
+            (
+                (ent.address() << 12) | offset,
+                PageTableFlags::from_entry(ent, true),
+                size,
+            )
+        } else {
+            (
+                ent.huge_address() | offset,
+                PageTableFlags::from_huge_entry(ent),
+                size,
+            )
+        })
+    }
+
+    #[inline]
+    pub unsafe fn virt_to_phys(&mut self, virt: u64) -> Option<(u64, PageTableFlags)> {
+        self.translate(virt).map(|(phys, flags, _)| (phys, flags))
+    }
+
+    /// Maps `count` pages, failing if `alloc_entry` does. The pages mapped before the failure
+    /// stay mapped.
+    #[inline]
+    pub unsafe fn map<E>(
         &mut self,
-        alloc_entry: AllocEntryFn,
+        alloc_entry: AllocEntryFn<E>,
         virt: u64,
         phys: u64,
         count: u64,
         flags: PageTableFlags,
-    ) {
+    ) -> Result<(), E> {
         assert_ne!(count, 0);
         for (phys, virt) in (0..count).map(|i| (phys + PAGE_SIZE * i, virt + PAGE_SIZE * i)) {
             let offs = PageTableIndices::new(virt);
-            let pdp = self.get_and_update_or_alloc(alloc_entry, offs.pml4, flags);
-            let pd = pdp.get_and_update_or_alloc(alloc_entry, offs.pdp, flags);
-            let pt = pd.get_and_update_or_alloc(alloc_entry, offs.pd, flags);
+            let pdp =
+                self.get_and_update_or_alloc(alloc_entry, offs.pml4, flags, PageSize::Size1GiB)?;
+            let pd =
+                pdp.get_and_update_or_alloc(alloc_entry, offs.pdp, flags, PageSize::Size2MiB)?;
+            let pt = pd.get_and_update_or_alloc(alloc_entry, offs.pd, flags, PageSize::Size4KiB)?;
             pt.entries[offs.pt] = flags.as_entry(true).with_address(phys >> 12);
         }
+        Ok(())
+    }
+
+    /// Maps `count` pages of `size`, replacing the tables that were mapped there, see
+    /// [`Self::map`].
+    #[inline]
+    pub unsafe fn map_huge<E>(
+        &mut self,
+        alloc_entry: AllocEntryFn<E>,
+        virt: u64,
+        phys: u64,
+        count: u64,
+        flags: PageTableFlags,
+        size: PageSize,
+    ) -> Result<(), E> {
+        if size == PageSize::Size4KiB {
+            return self.map(alloc_entry, virt, phys, count, flags);
+        }
+        assert_ne!(count, 0);
+        assert!(virt.is_multiple_of(size.size()) && phys.is_multiple_of(size.size()));
+        let len = size.size();
+        for (phys, virt) in (0..count).map(|i| (phys + len * i, virt + len * i)) {
+            let offs = PageTableIndices::new(virt);
+            let pdp =
+                self.get_and_update_or_alloc(alloc_entry, offs.pml4, flags, PageSize::Size1GiB)?;
+            let entry = if size == PageSize::Size1GiB {
+                &mut pdp.entries[offs.pdp]
+            } else {
+                let pd =
+                    pdp.get_and_update_or_alloc(alloc_entry, offs.pdp, flags, PageSize::Size2MiB)?;
+                &mut pd.entries[offs.pd]
+            };
+            *entry = flags.as_huge_entry(phys);
+        }
+        Ok(())
+    }
+
+    /// Maps `len` bytes with the largest pages up to `max_size` that `virt` and `phys` are
+    /// aligned to, see [`Self::map`].
+    pub unsafe fn map_range<E>(
+        &mut self,
+        alloc_entry: AllocEntryFn<E>,
+        virt: u64,
+        phys: u64,
+        len: u64,
+        flags: PageTableFlags,
+        max_size: PageSize,
+    ) -> Result<(), E> {
+        let mut offset = 0;
+        while offset < len {
+            let (virt, phys) = (virt + offset, phys + offset);
+            let size = [PageSize::Size1GiB, PageSize::Size2MiB]
+                .into_iter()
+                .find(|&v| {
+                    v <= max_size
+                        && (virt | phys).is_multiple_of(v.size())
+                        && len - offset >= v.size()
+                })
+                .unwrap_or(PageSize::Size4KiB);
+            self.map_huge(alloc_entry, virt, phys, 1, flags, size)?;
+            offset += size.size();
+        }
+        Ok(())
     }
 
+    /// Unmaps `count` pages, which must cover the huge pages within the range entirely.
     #[inline]
     pub unsafe fn unmap(&mut self, virt: u64, count: u64) {
         assert_ne!(count, 0);
-        for virt in (0..count).map(|i| virt + PAGE_SIZE * i) {
+        let end = virt + count * PAGE_SIZE;
+        let mut virt = virt;
+        while virt < end {
             core::arch::asm!("invlpg [{}]", in(reg) virt, options(nostack, preserves_flags));
-            let offs = PageTableIndices::new(virt);
-            let pdp = self.get(offs.pml4).unwrap();
-            let pd = pdp.get(offs.pdp).unwrap();
-            let pt = pd.get(offs.pd).unwrap();
-            assert!(pt.entries[offs.pt].present());
-            pt.entries[offs.pt] = PageTableEntry::new();
+            let (entry, size) = self.leaf(virt).unwrap();
+            assert!(
+                virt.is_multiple_of(size.size()) && end - virt >= size.size(),
+                "Partially unmapping the huge page at {virt:#X}"
+            );
+            *entry = PageTableEntry::new();
+            virt += size.size();
+        }
+    }
+
+    /// Unmaps whatever is mapped within `count` pages at `virt`, returning the number of pages
+    /// unmapped.
+    ///
+    /// The tables left empty are unlinked and handed to `free_table`. The translations to
+    /// invalidate are added to `flush`, which the caller has to flush.
+    pub unsafe fn unmap_range(
+        &mut self,
+        virt: u64,
+        count: u64,
+        free_table: FreeTableFn,
+        flush: &mut TlbFlush,
+    ) -> Result<u64, UnmapError> {
+        assert!(virt.is_multiple_of(PAGE_SIZE));
+        if count == 0 {
+            return Ok(0);
+        }
+        let last = virt + (count * PAGE_SIZE - 1);
+        let base = virt & 0xFFFF_0000_0000_0000;
+        assert_eq!(last & 0xFFFF_0000_0000_0000, base);
+
+        // Only the pages at either end can stick out, check them before changing anything.
+        for addr in [virt, last] {
+            if let (Some(v), _) = self.lookup(addr) {
+                if v.virt < virt || v.virt + (v.size.size() - 1) > last {
+                    return Err(UnmapError::PartialHugePage(v.virt));
+                }
+            }
+        }
+
+        Ok(self.unmap_level(4, base, virt, last, free_table, flush))
+    }
+
+    unsafe fn unmap_level(
+        &mut self,
+        level: u32,
+        base: u64,
+        first: u64,
+        last: u64,
+        free_table: FreeTableFn,
+        flush: &mut TlbFlush,
+    ) -> u64 {
+        let shift = 3 + 9 * level;
+        let mut pages = 0;
+        for i in ((first - base) >> shift) as usize..=((last - base) >> shift) as usize {
+            let entry_base = base + ((i as u64) << shift);
+            let entry = &mut self.entries[i];
+            if !entry.present() {
+                continue;
+            }
+            if level == 1 || entry.huge_or_pat() {
+                pages += 1 << (shift - 12);
+            } else {
+                let phys = entry.address() << 12;
+                let table = &mut *((phys + VIRT_OFF) as *mut Self);
+                pages += table.unmap_level(
+                    level - 1,
+                    entry_base,
+                    first.max(entry_base),
+                    last.min(entry_base + ((1 << shift) - 1)),
+                    free_table,
+                    flush,
+                );
+                if table.entries.iter().any(PageTableEntry::present) {
+                    continue;
+                }
+                free_table(phys);
+            }
+            *entry = PageTableEntry::new();
+            flush.add(entry_base);
         }
+        pages
     }
 
+    /// Maps the first 4 GiB of physical memory at [`PHYS_VIRT_OFFSET`] and the first 2 GiB at
+    /// [`KERNEL_VIRT_OFFSET`] with the largest pages supported, leaving the null page unmapped.
     #[inline]
-    pub unsafe fn map_higher_half(&mut self, alloc_entry: AllocEntryFn) {
-        self.map(
-            alloc_entry,
-            PHYS_VIRT_OFFSET + PAGE_SIZE,
-            PAGE_SIZE,
-            0xFFFFF,
-            PageTableFlags::new_present().with_writable(true),
-        );
-        self.map(
-            alloc_entry,
-            KERNEL_VIRT_OFFSET + PAGE_SIZE,
-            PAGE_SIZE,
-            0x7FFFF,
-            PageTableFlags::new_present().with_writable(true),
-        );
+    pub unsafe fn map_higher_half<E>(&mut self, alloc_entry: AllocEntryFn<E>) -> Result<(), E> {
+        let flags = PageTableFlags::new_present().with_writable(true);
+        let max_size = PageSize::largest_supported();
+        for (virt, len) in [
+            (PHYS_VIRT_OFFSET, 0x1_0000_0000),
+            (KERNEL_VIRT_OFFSET, 0x8000_0000),
+        ] {
+            self.map_range(
+                alloc_entry,
+                virt + PAGE_SIZE,
+                PAGE_SIZE,
+                len - PAGE_SIZE,
+                flags,
+                max_size,
+            )?;
+        }
+        Ok(())
     }
 }
 
@@ -2,8 +2,14 @@
 
 #![deny(warnings, clippy::nursery, unused_extern_crates)]
 
+use std::{
+    cell::{Cell, RefCell},
+    convert::Infallible,
+};
+
 use amd64::paging::{
-    PageTable, PageTableEntry, PageTableFlags, KERNEL_VIRT_OFFSET, PAGE_SIZE, PHYS_VIRT_OFFSET,
+    Mapping, PageSize, PageTable, PageTableEntry, PageTableFlags, TlbFlush, UnmapError,
+    KERNEL_VIRT_OFFSET, PAGE_SIZE, PHYS_VIRT_OFFSET,
 };
 
 #[test]
@@ -60,10 +66,49 @@ fn test_flags() {
             .with_pcd(true)
             .with_huge_or_pat(true)
     );
+    assert_eq!(
+        PageTableFlags::new_present()
+            .with_user(true)
+            .with_executable(false)
+            .as_entry(true),
+        PageTableEntry::new()
+            .with_present(true)
+            .with_user(true)
+            .with_no_execute(true)
+    );
+    assert_eq!(
+        PageTableFlags::from_entry(
+            &PageTableEntry::new()
+                .with_present(true)
+                .with_no_execute(true),
+            true
+        ),
+        PageTableFlags::new_present().with_executable(false)
+    );
 }
 
-fn alloc_entry() -> u64 {
-    Box::leak(Box::new(PageTable::<0>::new())) as *mut _ as u64
+#[test]
+fn test_huge_flags() {
+    let flags = PageTableFlags::new_present()
+        .with_writable(true)
+        .with_pat_entry(5);
+    let entry = flags.as_huge_entry(0x20_0000);
+    assert_eq!(
+        entry,
+        PageTableEntry::new()
+            .with_present(true)
+            .with_writable(true)
+            .with_pwt(true)
+            .with_huge_or_pat(true)
+            .with_address(0x201)
+    );
+    assert!(entry.is_huge());
+    assert_eq!(entry.huge_address(), 0x20_0000);
+    assert_eq!(PageTableFlags::from_huge_entry(&entry), flags);
+}
+
+fn alloc_entry() -> Result<u64, Infallible> {
+    Ok(Box::leak(Box::new(PageTable::<0>::new())) as *mut _ as u64)
 }
 
 #[test]
@@ -84,7 +129,8 @@ fn test_map() {
             0x20_0000,
             1,
             PageTableFlags::new_present(),
-        );
+        )
+        .unwrap();
         assert_eq!(
             pml4.virt_to_phys(0x20_0000),
             Some((0x20_0000, PageTableFlags::new_present()))
@@ -96,10 +142,22 @@ fn test_map() {
 fn test_map_higher_half() {
     unsafe {
         let mut pml4 = Box::new(PageTable::<0>::new());
-        pml4.map_higher_half(&alloc_entry);
+        pml4.map_higher_half(&alloc_entry).unwrap();
 
         assert_eq!(pml4.virt_to_phys(PHYS_VIRT_OFFSET), None);
         assert_eq!(pml4.virt_to_phys(KERNEL_VIRT_OFFSET), None);
+        assert_eq!(
+            pml4.translate(PHYS_VIRT_OFFSET + PAGE_SIZE).map(|v| v.2),
+            Some(PageSize::Size4KiB)
+        );
+        assert_eq!(
+            pml4.translate(PHYS_VIRT_OFFSET + 0x20_0000).map(|v| v.2),
+            Some(PageSize::Size2MiB)
+        );
+        assert_eq!(
+            pml4.translate(PHYS_VIRT_OFFSET + 0x4000_0000).map(|v| v.2),
+            Some(PageSize::largest_supported())
+        );
 
         for i in 1..0xFFFFF {
             assert_eq!(
@@ -122,3 +180,343 @@ fn test_map_higher_half() {
         }
     }
 }
+
+#[test]
+fn test_map_no_execute() {
+    unsafe {
+        let mut pml4 = Box::new(PageTable::<0>::new());
+        let flags = PageTableFlags::new_present()
+            .with_writable(true)
+            .with_user(true)
+            .with_executable(false);
+        pml4.map(&alloc_entry, 0xC000_0000, 0x1000, 2, flags)
+            .unwrap();
+        pml4.map(
+            &alloc_entry,
+            0xC000_2000,
+            0x3000,
+            1,
+            PageTableFlags::new_present().with_user(true),
+        )
+        .unwrap();
+
+        assert_eq!(pml4.virt_to_phys(0xC000_0000), Some((0x1000, flags)));
+        assert_eq!(pml4.virt_to_phys(0xC000_1000), Some((0x2000, flags)));
+        assert_eq!(
+            pml4.virt_to_phys(0xC000_2000),
+            Some((0x3000, PageTableFlags::new_present().with_user(true)))
+        );
+        assert!(!pml4.entries[0].no_execute());
+    }
+}
+
+#[test]
+fn test_map_copy_on_write() {
+    unsafe {
+        let mut pml4 = Box::new(PageTable::<0>::new());
+        let flags = PageTableFlags::new_present()
+            .with_user(true)
+            .with_copy_on_write(true);
+        pml4.map(&alloc_entry, 0xC000_0000, 0x1000, 1, flags)
+            .unwrap();
+
+        assert_eq!(pml4.virt_to_phys(0xC000_0000), Some((0x1000, flags)));
+        assert!(!pml4.entries[0].copy_on_write());
+        assert_eq!(
+            flags.as_entry(true),
+            PageTableEntry::new()
+                .with_present(true)
+                .with_user(true)
+                .with_copy_on_write(true)
+        );
+    }
+}
+
+#[test]
+fn test_map_huge() {
+    unsafe {
+        let mut pml4 = Box::new(PageTable::<0>::new());
+        let flags = PageTableFlags::new_present().with_writable(true);
+        pml4.map_huge(
+            &alloc_entry,
+            0x4000_0000,
+            0x20_0000,
+            2,
+            flags,
+            PageSize::Size2MiB,
+        )
+        .unwrap();
+        pml4.map_huge(
+            &alloc_entry,
+            0x8000_0000,
+            0x4000_0000,
+            1,
+            flags,
+            PageSize::Size1GiB,
+        )
+        .unwrap();
+
+        assert_eq!(
+            pml4.translate(0x4000_0000),
+            Some((0x20_0000, flags, PageSize::Size2MiB))
+        );
+        assert_eq!(
+            pml4.translate(0x403F_F123),
+            Some((0x5F_F123, flags, PageSize::Size2MiB))
+        );
+        assert_eq!(pml4.translate(0x4040_0000), None);
+        assert_eq!(
+            pml4.translate(0xBFFF_F000),
+            Some((0x7FFF_F000, flags, PageSize::Size1GiB))
+        );
+        assert_eq!(pml4.virt_to_phys(0x8000_1000), Some((0x4000_1000, flags)));
+    }
+}
+
+#[test]
+fn test_split_huge() {
+    unsafe {
+        let mut pml4 = Box::new(PageTable::<0>::new());
+        let flags = PageTableFlags::new_present().with_writable(true);
+        pml4.map_huge(&alloc_entry, 0x4000_0000, 0, 1, flags, PageSize::Size1GiB)
+            .unwrap();
+        let mmio = flags.with_pat_entry(1);
+        pml4.map(&alloc_entry, 0x4060_1000, 0x1234_5000, 1, mmio)
+            .unwrap();
+
+        assert_eq!(
+            pml4.translate(0x4060_1000),
+            Some((0x1234_5000, mmio, PageSize::Size4KiB))
+        );
+        assert_eq!(
+            pml4.translate(0x4060_2000),
+            Some((0x60_2000, flags, PageSize::Size4KiB))
+        );
+        assert_eq!(
+            pml4.translate(0x4040_0000),
+            Some((0x40_0000, flags, PageSize::Size2MiB))
+        );
+        assert_eq!(
+            pml4.translate(0x7FFF_F000),
+            Some((0x3FFF_F000, flags, PageSize::Size2MiB))
+        );
+    }
+}
+
+#[test]
+fn test_map_out_of_memory() {
+    unsafe {
+        let mut pml4 = Box::new(PageTable::<0>::new());
+        let flags = PageTableFlags::new_present();
+        let left = Cell::new(2);
+        let alloc_some = || {
+            if left.get() == 0 {
+                return Err(());
+            }
+            left.set(left.get() - 1);
+            alloc_entry().map_err(|v| match v {})
+        };
+
+        // The PDPT and the PD fit, the PT does not.
+        assert_eq!(pml4.map(&alloc_some, 0x20_0000, 0x1000, 1, flags), Err(()));
+        assert_eq!(pml4.virt_to_phys(0x20_0000), None);
+
+        assert_eq!(
+            pml4.map_huge(
+                &alloc_some,
+                0x40_0000,
+                0x20_0000,
+                1,
+                flags,
+                PageSize::Size2MiB
+            ),
+            Ok(())
+        );
+        // Splitting needs a table as well, the huge page stays as it was without one.
+        assert_eq!(pml4.map(&alloc_some, 0x40_1000, 0x5000, 1, flags), Err(()));
+        assert_eq!(pml4.virt_to_phys(0x40_1000), Some((0x20_1000, flags)));
+    }
+}
+
+#[test]
+fn test_map_range() {
+    unsafe {
+        let mut pml4 = Box::new(PageTable::<0>::new());
+        let flags = PageTableFlags::new_present().with_pat_entry(2);
+        pml4.map_range(
+            &alloc_entry,
+            0x1F_F000,
+            0x1F_F000,
+            0x40_2000,
+            flags,
+            PageSize::Size1GiB,
+        )
+        .unwrap();
+
+        assert_eq!(
+            pml4.translate(0x1F_F000),
+            Some((0x1F_F000, flags, PageSize::Size4KiB))
+        );
+        assert_eq!(
+            pml4.translate(0x20_0000),
+            Some((0x20_0000, flags, PageSize::Size2MiB))
+        );
+        assert_eq!(
+            pml4.translate(0x40_0000),
+            Some((0x40_0000, flags, PageSize::Size2MiB))
+        );
+        assert_eq!(
+            pml4.translate(0x60_0000),
+            Some((0x60_0000, flags, PageSize::Size4KiB))
+        );
+        assert_eq!(pml4.translate(0x60_1000), None);
+    }
+}
+
+#[test]
+fn test_mappings() {
+    unsafe {
+        let mut pml4 = Box::new(PageTable::<0>::new());
+        let flags = PageTableFlags::new_present();
+        pml4.map(&alloc_entry, 0x1000, 0x5000, 2, flags).unwrap();
+        pml4.map_huge(
+            &alloc_entry,
+            0x8000_0000,
+            0x40_0000,
+            1,
+            flags,
+            PageSize::Size2MiB,
+        )
+        .unwrap();
+        pml4.map(&alloc_entry, PHYS_VIRT_OFFSET, 0x9000, 1, flags)
+            .unwrap();
+        pml4.map(&alloc_entry, KERNEL_VIRT_OFFSET, 0xA000, 1, flags)
+            .unwrap();
+
+        let mapping = |virt, phys, size| Mapping {
+            virt,
+            phys,
+            flags,
+            size,
+        };
+        assert_eq!(
+            pml4.mappings(0, 0x8010_0000).collect::<Vec<_>>(),
+            [
+                mapping(0x1000, 0x5000, PageSize::Size4KiB),
+                mapping(0x2000, 0x6000, PageSize::Size4KiB),
+                mapping(0x8000_0000, 0x40_0000, PageSize::Size2MiB),
+            ]
+        );
+        assert_eq!(
+            pml4.mappings(0x2000, 0x8000_1000).collect::<Vec<_>>(),
+            [
+                mapping(0x2000, 0x6000, PageSize::Size4KiB),
+                mapping(0x8000_0000, 0x40_0000, PageSize::Size2MiB),
+            ]
+        );
+        assert_eq!(pml4.mappings(0x1000, 0).count(), 0);
+        assert_eq!(
+            pml4.mappings(0x8000_0000_0000, PHYS_VIRT_OFFSET)
+                .collect::<Vec<_>>(),
+            [
+                mapping(PHYS_VIRT_OFFSET, 0x9000, PageSize::Size4KiB),
+                mapping(KERNEL_VIRT_OFFSET, 0xA000, PageSize::Size4KiB),
+            ]
+        );
+    }
+}
+
+#[test]
+fn test_unmap_range() {
+    unsafe {
+        let mut pml4 = Box::new(PageTable::<0>::new());
+        let flags = PageTableFlags::new_present();
+        pml4.map(&alloc_entry, 0x1000, 0x1000, 3, flags).unwrap();
+        pml4.map(&alloc_entry, 0x8000, 0x8000, 1, flags).unwrap();
+        pml4.map(&alloc_entry, 0x40_0000, 0x1000, 1, flags).unwrap();
+
+        let freed = RefCell::new(Vec::new());
+        let free_table = |phys| freed.borrow_mut().push(phys);
+        let mut flush = TlbFlush::new();
+
+        // Holes are skipped and the table still holding a mapping is kept.
+        assert_eq!(pml4.unmap_range(0, 8, &free_table, &mut flush), Ok(3));
+        assert_eq!(flush.addresses(), Some(&[0x1000, 0x2000, 0x3000][..]));
+        assert!(freed.borrow().is_empty());
+        assert_eq!(pml4.virt_to_phys(0x2000), None);
+        assert_eq!(pml4.virt_to_phys(0x8000), Some((0x8000, flags)));
+
+        // Emptying the lower levels frees them, up to the top level one.
+        let mut flush = TlbFlush::new();
+        assert_eq!(
+            pml4.unmap_range(0x8000, 0x400, &free_table, &mut flush),
+            Ok(2)
+        );
+        assert_eq!(
+            flush.addresses(),
+            Some(&[0x8000, 0, 0x40_0000, 0x40_0000, 0, 0][..])
+        );
+        assert_eq!(freed.borrow().len(), 4);
+        assert!(!pml4.entries[0].present());
+        assert_eq!(pml4.mappings(0, 0x8000_0000_0000).count(), 0);
+    }
+}
+
+#[test]
+fn test_unmap_range_huge() {
+    unsafe {
+        let mut pml4 = Box::new(PageTable::<0>::new());
+        let flags = PageTableFlags::new_present();
+        pml4.map_huge(
+            &alloc_entry,
+            0x4000_0000,
+            0x4000_0000,
+            1,
+            flags,
+            PageSize::Size1GiB,
+        )
+        .unwrap();
+        pml4.map_huge(&alloc_entry, 0x8000_0000, 0, 2, flags, PageSize::Size2MiB)
+            .unwrap();
+
+        let free_table = |_| {};
+        let mut flush = TlbFlush::new();
+        assert_eq!(
+            pml4.unmap_range(0x4000_0000, 0x200, &free_table, &mut flush),
+            Err(UnmapError::PartialHugePage(0x4000_0000))
+        );
+        assert_eq!(
+            pml4.unmap_range(0x7FFF_F000, 0x201, &free_table, &mut flush),
+            Err(UnmapError::PartialHugePage(0x4000_0000))
+        );
+        assert_eq!(
+            pml4.unmap_range(0x8000_0000, 0x201, &free_table, &mut flush),
+            Err(UnmapError::PartialHugePage(0x8020_0000))
+        );
+        assert_eq!(flush.addresses(), Some(&[][..]));
+
+        assert_eq!(
+            pml4.unmap_range(0x4000_0000, 0x40200, &free_table, &mut flush),
+            Ok(0x40200)
+        );
+        assert_eq!(flush.addresses(), Some(&[0x4000_0000, 0x8000_0000][..]));
+        assert_eq!(
+            pml4.translate(0x8020_0000),
+            Some((0x20_0000, flags, PageSize::Size2MiB))
+        );
+    }
+}
+
+#[test]
+fn test_tlb_flush() {
+    let mut flush = TlbFlush::new();
+    for i in 0..TlbFlush::CAPACITY as u64 {
+        flush.add(i * PAGE_SIZE);
+    }
+    assert_eq!(
+        flush.addresses().map(<[u64]>::len),
+        Some(TlbFlush::CAPACITY)
+    );
+    flush.add(0);
+    assert_eq!(flush.addresses(), None);
+}
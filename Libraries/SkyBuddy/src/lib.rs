@@ -0,0 +1,278 @@
+// Copyright (c) ChefKiss 2021-2025. Licensed under the Thou Shalt Not Profit License version 1.5. See LICENSE for details.
+
+#![no_std]
+#![deny(warnings, clippy::nursery, unused_extern_crates)]
+
+pub const PAGE_SIZE: u64 = 0x1000;
+/// Order of the largest block, 256 MiB.
+pub const MAX_ORDER: u32 = 16;
+
+const NOT_FREE: u8 = u8::MAX;
+const NIL: u64 = u64::MAX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Zone {
+    /// Reachable by legacy DMA.
+    Below1MiB,
+    /// Reachable by 32-bit DMA.
+    Below4GiB,
+    Above4GiB,
+}
+
+impl Zone {
+    pub const ALL: [Self; 3] = [Self::Below1MiB, Self::Below4GiB, Self::Above4GiB];
+
+    #[inline]
+    #[must_use]
+    pub const fn of(addr: u64) -> Self {
+        if addr < 0x10_0000 {
+            Self::Below1MiB
+        } else if addr < 0x1_0000_0000 {
+            Self::Below4GiB
+        } else {
+            Self::Above4GiB
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn end(self) -> u64 {
+        match self {
+            Self::Below1MiB => 0x10_0000,
+            Self::Below4GiB => 0x1_0000_0000,
+            Self::Above4GiB => u64::MAX,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ZoneStats {
+    pub total_pages: u64,
+    pub free_pages: u64,
+}
+
+/// Links of a free block, stored in its first page.
+#[repr(C)]
+struct FreeBlock {
+    next: u64,
+    prev: u64,
+}
+
+/// Binary buddy allocator over physical pages, keeping separate free lists per [`Zone`].
+pub struct BuddyAllocator {
+    virt_offset: u64,
+    /// Order of the free block starting at each page, [`NOT_FREE`] otherwise.
+    orders: &'static mut [u8],
+    free_lists: [[u64; MAX_ORDER as usize + 1]; 3],
+    zones: [ZoneStats; 3],
+    pub highest_addr: u64,
+    pub total_pages: u64,
+    pub free_pages: u64,
+}
+
+impl BuddyAllocator {
+    /// Creates an allocator over the `(base, length)` ranges, accessed at `virt_offset`.
+    ///
+    /// Memory up to `end` can be added later on with [`Self::add_range`]. The page metadata is
+    /// placed at the start of the first range large enough to hold it. The page at address 0 is
+    /// never handed out, as a null pointer means failure.
+    ///
+    /// # Safety
+    ///
+    /// The ranges must not overlap and must be unused memory mapped at `virt_offset`.
+    pub unsafe fn new(
+        ranges: impl Iterator<Item = (u64, u64)> + Clone,
+        end: u64,
+        virt_offset: u64,
+    ) -> Self {
+        let highest_addr = ranges
+            .clone()
+            .map(|(base, len)| base + len)
+            .fold(end, u64::max);
+        let meta_len = (highest_addr / PAGE_SIZE).next_multiple_of(PAGE_SIZE);
+        let meta_base = ranges
+            .clone()
+            .map(|(base, len)| (base.next_multiple_of(PAGE_SIZE), base + len))
+            .find(|&(base, end)| end.saturating_sub(base) >= meta_len)
+            .expect("No range can hold the page metadata")
+            .0;
+
+        let orders = core::slice::from_raw_parts_mut(
+            (meta_base + virt_offset) as *mut u8,
+            (highest_addr / PAGE_SIZE) as usize,
+        );
+        orders.fill(NOT_FREE);
+
+        let mut ret = Self {
+            virt_offset,
+            orders,
+            free_lists: [[NIL; MAX_ORDER as usize + 1]; 3],
+            zones: [ZoneStats::default(); 3],
+            highest_addr,
+            total_pages: 0,
+            free_pages: 0,
+        };
+
+        for (base, len) in ranges {
+            let mut start = base.next_multiple_of(PAGE_SIZE);
+            if start == meta_base {
+                start += meta_len;
+            }
+            ret.add_range(start, (base + len).saturating_sub(start));
+        }
+
+        ret
+    }
+
+    /// Hands the whole pages of `base..base + len` over to the allocator, returning their count.
+    ///
+    /// # Safety
+    ///
+    /// The range must be unused memory mapped at the virtual offset, below
+    /// [`Self::highest_addr`], and must not overlap memory the allocator already has.
+    pub unsafe fn add_range(&mut self, base: u64, len: u64) -> u64 {
+        let start = base.next_multiple_of(PAGE_SIZE).max(PAGE_SIZE);
+        let end = ((base + len) & !(PAGE_SIZE - 1)).min(self.highest_addr);
+        if start >= end {
+            return 0;
+        }
+
+        let mut addr = start;
+        while addr < end {
+            let zone = Zone::of(addr);
+            let len = (end.min(zone.end()) - addr) / PAGE_SIZE;
+            self.zones[zone as usize].total_pages += len;
+            self.total_pages += len;
+            addr += len * PAGE_SIZE;
+        }
+        self.release(start, end);
+        (end - start) / PAGE_SIZE
+    }
+
+    /// Frees `start..end` as the largest aligned blocks that fit, merging them with their buddies.
+    unsafe fn release(&mut self, start: u64, end: u64) {
+        let mut addr = start;
+        while addr < end {
+            let zone = Zone::of(addr);
+            let pages = (end.min(zone.end()) - addr) / PAGE_SIZE;
+            let order = (addr / PAGE_SIZE)
+                .trailing_zeros()
+                .min(pages.ilog2())
+                .min(MAX_ORDER);
+            self.zones[zone as usize].free_pages += 1 << order;
+            self.free_pages += 1 << order;
+            self.free_block(addr, order);
+            addr += PAGE_SIZE << order;
+        }
+    }
+
+    #[inline]
+    const fn block(&self, addr: u64) -> *mut FreeBlock {
+        (addr + self.virt_offset) as *mut FreeBlock
+    }
+
+    unsafe fn link(&mut self, zone: Zone, addr: u64, order: u32) {
+        let head = &mut self.free_lists[zone as usize][order as usize];
+        let next = core::mem::replace(head, addr);
+        self.block(addr).write(FreeBlock { next, prev: NIL });
+        if next != NIL {
+            (*self.block(next)).prev = addr;
+        }
+        self.orders[(addr / PAGE_SIZE) as usize] = order as u8;
+    }
+
+    unsafe fn unlink(&mut self, zone: Zone, addr: u64, order: u32) {
+        let FreeBlock { next, prev } = self.block(addr).read();
+        if prev == NIL {
+            self.free_lists[zone as usize][order as usize] = next;
+        } else {
+            (*self.block(prev)).next = next;
+        }
+        if next != NIL {
+            (*self.block(next)).prev = prev;
+        }
+        self.orders[(addr / PAGE_SIZE) as usize] = NOT_FREE;
+    }
+
+    unsafe fn free_block(&mut self, mut addr: u64, mut order: u32) {
+        let zone = Zone::of(addr);
+        while order < MAX_ORDER {
+            let buddy = addr ^ (PAGE_SIZE << order);
+            if Zone::of(buddy) != zone
+                || self.orders.get((buddy / PAGE_SIZE) as usize) != Some(&(order as u8))
+            {
+                break;
+            }
+            self.unlink(zone, buddy, order);
+            addr = addr.min(buddy);
+            order += 1;
+        }
+        self.link(zone, addr, order);
+    }
+
+    /// Allocates `count` contiguous pages from `zone`, returning null if it has no room.
+    ///
+    /// # Safety
+    ///
+    /// The memory given to the allocator must still be mapped at its virtual offset.
+    pub unsafe fn alloc_zone(&mut self, count: u64, zone: Zone) -> *mut u8 {
+        if count == 0 {
+            return core::ptr::null_mut();
+        }
+        let order = count.next_power_of_two().ilog2();
+        let lists = &self.free_lists[zone as usize];
+        let Some(found) = (order..=MAX_ORDER).find(|&v| lists[v as usize] != NIL) else {
+            return core::ptr::null_mut();
+        };
+        let addr = lists[found as usize];
+        self.unlink(zone, addr, found);
+        self.zones[zone as usize].free_pages -= 1 << found;
+        self.free_pages -= 1 << found;
+        self.release(addr + count * PAGE_SIZE, addr + (PAGE_SIZE << found));
+        addr as *mut u8
+    }
+
+    /// Allocates `count` contiguous pages, preferring memory below 4 GiB and keeping memory
+    /// below 1 MiB as a last resort.
+    ///
+    /// # Safety
+    ///
+    /// See [`Self::alloc_zone`].
+    pub unsafe fn alloc(&mut self, count: u64) -> *mut u8 {
+        [Zone::Below4GiB, Zone::Above4GiB, Zone::Below1MiB]
+            .into_iter()
+            .map(|zone| self.alloc_zone(count, zone))
+            .find(|v| !v.is_null())
+            .unwrap_or(core::ptr::null_mut())
+    }
+
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by this allocator for `count` pages and not be used anymore.
+    pub unsafe fn free(&mut self, ptr: *mut u8, count: u64) {
+        assert_eq!(ptr as u64 & (PAGE_SIZE - 1), 0);
+        debug_assert!(self.is_allocated(ptr, count));
+
+        self.release(ptr as u64, ptr as u64 + count * PAGE_SIZE);
+    }
+
+    fn is_free(&self, page: u64) -> bool {
+        (0..=MAX_ORDER).any(|order| {
+            let head = page & !((1 << order) - 1);
+            self.orders.get(head as usize) == Some(&(order as u8))
+        })
+    }
+
+    pub fn is_allocated(&self, ptr: *mut u8, count: u64) -> bool {
+        assert_eq!(ptr as u64 & (PAGE_SIZE - 1), 0);
+
+        let idx = ptr as u64 / PAGE_SIZE;
+        (idx..idx + count).all(|v| !self.is_free(v))
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn zone_stats(&self, zone: Zone) -> ZoneStats {
+        self.zones[zone as usize]
+    }
+}
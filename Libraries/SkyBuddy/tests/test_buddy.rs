@@ -0,0 +1,143 @@
+// Copyright (c) ChefKiss 2021-2025. Licensed under the Thou Shalt Not Profit License version 1.5. See LICENSE for details.
+
+#![deny(warnings, clippy::nursery, unused_extern_crates)]
+
+use skybuddy::{BuddyAllocator, Zone, ZoneStats, MAX_ORDER, PAGE_SIZE};
+
+const MEM_LEN: u64 = 0x80_0000;
+
+fn new_memory() -> u64 {
+    let layout = std::alloc::Layout::from_size_align(MEM_LEN as _, PAGE_SIZE as _).unwrap();
+    let mem = unsafe { std::alloc::alloc_zeroed(layout) };
+    assert!(!mem.is_null());
+    mem as u64
+}
+
+/// 1 MiB below the zone boundary and 7 MiB above, with a page of metadata at address 0.
+fn new_allocator() -> BuddyAllocator {
+    unsafe {
+        BuddyAllocator::new(
+            [(0, 0x10_0000), (0x10_0000, MEM_LEN - 0x10_0000)].into_iter(),
+            MEM_LEN,
+            new_memory(),
+        )
+    }
+}
+
+#[test]
+fn test_new() {
+    let pmm = new_allocator();
+    assert_eq!(pmm.highest_addr, MEM_LEN);
+    assert_eq!(pmm.total_pages, MEM_LEN / PAGE_SIZE - 1);
+    assert_eq!(pmm.free_pages, pmm.total_pages);
+    assert_eq!(
+        pmm.zone_stats(Zone::Below1MiB),
+        ZoneStats {
+            total_pages: 0xFF,
+            free_pages: 0xFF
+        }
+    );
+    assert_eq!(
+        pmm.zone_stats(Zone::Below4GiB),
+        ZoneStats {
+            total_pages: 0x700,
+            free_pages: 0x700
+        }
+    );
+    assert_eq!(pmm.zone_stats(Zone::Above4GiB), ZoneStats::default());
+    assert!(pmm.is_allocated(core::ptr::null_mut(), 1));
+}
+
+#[test]
+fn test_zones() {
+    let mut pmm = new_allocator();
+    unsafe {
+        let ptr = pmm.alloc(1);
+        assert_eq!(Zone::of(ptr as u64), Zone::Below4GiB);
+        let ptr = pmm.alloc_zone(4, Zone::Below1MiB);
+        assert_eq!(Zone::of(ptr as u64), Zone::Below1MiB);
+        assert!(pmm.alloc_zone(1, Zone::Above4GiB).is_null());
+    }
+    assert_eq!(pmm.zone_stats(Zone::Below1MiB).free_pages, 0xFF - 4);
+    assert_eq!(pmm.zone_stats(Zone::Below4GiB).free_pages, 0x700 - 1);
+}
+
+#[test]
+fn test_exhaust_zone() {
+    let mut pmm = new_allocator();
+    unsafe {
+        for _ in 0..0xFF {
+            let ptr = pmm.alloc_zone(1, Zone::Below1MiB);
+            assert!(!ptr.is_null());
+            assert_eq!(Zone::of(ptr as u64), Zone::Below1MiB);
+        }
+        assert!(pmm.alloc_zone(1, Zone::Below1MiB).is_null());
+        assert_eq!(pmm.zone_stats(Zone::Below1MiB).free_pages, 0);
+    }
+}
+
+#[test]
+fn test_alloc_free() {
+    let mut pmm = new_allocator();
+    unsafe {
+        let ptr = pmm.alloc(3);
+        assert!(!ptr.is_null());
+        assert!((ptr as u64).is_multiple_of(PAGE_SIZE));
+        assert!(pmm.is_allocated(ptr, 3));
+        assert_eq!(pmm.free_pages, pmm.total_pages - 3);
+
+        // The unused page of the 4 page block is handed out next.
+        let next = pmm.alloc(1);
+        assert_eq!(next as u64, ptr as u64 + 3 * PAGE_SIZE);
+
+        pmm.free(ptr, 3);
+        pmm.free(next, 1);
+        assert!(!pmm.is_allocated(ptr, 1));
+        assert_eq!(pmm.free_pages, pmm.total_pages);
+    }
+}
+
+#[test]
+fn test_coalesce() {
+    let mut pmm = new_allocator();
+    unsafe {
+        let pages: Vec<_> = (0..0x700)
+            .map(|_| pmm.alloc_zone(1, Zone::Below4GiB))
+            .collect();
+        assert!(pages.iter().all(|v| !v.is_null()));
+        assert!(pmm.alloc_zone(1, Zone::Below4GiB).is_null());
+
+        for ptr in pages {
+            pmm.free(ptr, 1);
+        }
+        let ptr = pmm.alloc_zone(0x400, Zone::Below4GiB);
+        assert_eq!(ptr as u64, 0x40_0000);
+    }
+}
+
+#[test]
+fn test_too_large() {
+    let mut pmm = new_allocator();
+    unsafe {
+        assert!(pmm.alloc(0).is_null());
+        assert!(pmm.alloc((1 << MAX_ORDER) + 1).is_null());
+        assert!(pmm.alloc(0x800).is_null());
+    }
+    assert_eq!(pmm.free_pages, pmm.total_pages);
+}
+
+#[test]
+fn test_add_range() {
+    let mut pmm =
+        unsafe { BuddyAllocator::new([(0, 0x40_0000)].into_iter(), MEM_LEN, new_memory()) };
+    assert_eq!(pmm.highest_addr, MEM_LEN);
+    assert_eq!(pmm.total_pages, 0x3FF);
+    unsafe {
+        assert!(pmm.alloc(0x400).is_null());
+        assert_eq!(pmm.add_range(0x40_0800, 0x40_0000), 0x3FF);
+        assert_eq!(pmm.add_range(0x40_0000, 0x800), 0);
+        assert_eq!(pmm.add_range(0x40_0000, 0x1000), 1);
+        assert_eq!(pmm.total_pages, 0x7FF);
+        assert_eq!(pmm.alloc(0x400) as u64, 0x40_0000);
+    }
+}
@@ -13,26 +13,47 @@ use serde::{Deserialize, Serialize};
 pub mod msg;
 pub mod osdtentry;
 pub mod osvalue;
+pub mod snapshot;
 pub mod syscall;
 #[cfg(feature = "userspace")]
 pub mod userspace;
 
 extern crate alloc;
 
-pub const USER_VIRT_OFFSET: u64 = 0xC000_0000;
-
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct SKExtension {
     pub identifier: String,
     pub personalities: HashMap<String, HashMap<String, osvalue::OSValue>>,
 }
 
+impl SKExtension {
+    /// Memory limit of the process spawned for `personality`, see
+    /// [`osdtentry::SKEXT_MEMORY_LIMIT_KEY`].
+    pub fn memory_limit(&self, personality: &str) -> Result<Option<u64>, osvalue::OSValueError> {
+        self.personalities
+            .get(personality)
+            .and_then(|v| v.get(osdtentry::SKEXT_MEMORY_LIMIT_KEY))
+            .map(|v| <&u64>::try_from(v).copied())
+            .transpose()
+    }
+}
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
-pub struct SKExtensions(pub Vec<(SKExtension, Vec<u8>)>);
+pub struct SKExtensions {
+    pub extensions: Vec<(SKExtension, Vec<u8>)>,
+    /// Shared objects extensions may depend on, keyed by the name they are needed as.
+    pub libraries: HashMap<String, Vec<u8>>,
+}
 
 impl SKExtensions {
-    pub const fn new(inner: Vec<(SKExtension, Vec<u8>)>) -> Self {
-        Self(inner)
+    pub const fn new(
+        extensions: Vec<(SKExtension, Vec<u8>)>,
+        libraries: HashMap<String, Vec<u8>>,
+    ) -> Self {
+        Self {
+            extensions,
+            libraries,
+        }
     }
 }
 
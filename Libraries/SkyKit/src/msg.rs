@@ -3,7 +3,7 @@
 use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "userspace")]
-use super::syscall::SystemCall;
+use super::syscall::{raw, MapFlags, SystemCall, SystemCallError};
 
 #[derive(Debug, Clone)]
 pub struct Message {
@@ -19,46 +19,33 @@ impl Message {
     pub const fn new(id: u64, pid: u64, data: &'static [u8]) -> Self {
         Self { id, pid, data }
     }
-
-    #[cfg(feature = "userspace")]
-    #[inline]
-    #[must_use]
-    pub const fn new(pid: u64, data: &'static [u8]) -> Self {
-        Self { id: 0, pid, data }
-    }
 }
 
 #[cfg(feature = "userspace")]
 impl Message {
     #[must_use]
     pub unsafe fn recv() -> Self {
-        let (mut id, mut pid): (u64, u64);
-        let (mut ptr, mut len): (u64, u64);
-        core::arch::asm!(
-            "int 249",
-            in("rdi") SystemCall::MsgRecv as u64,
-            out("rax") id,
-            lateout("rdi") pid,
-            out("rsi") ptr,
-            out("rdx") len,
-            options(nostack),
-        );
+        let ret = raw::msg_recv().unwrap();
         Self {
-            id,
-            pid,
-            data: core::slice::from_raw_parts(ptr as *const u8, len as _),
+            id: ret.id,
+            pid: ret.pid,
+            data: core::slice::from_raw_parts(ret.ptr as *const u8, ret.len as _),
         }
     }
 
-    pub unsafe fn send(self) {
-        core::arch::asm!(
-            "int 249",
-            in("rdi") SystemCall::MsgSend as u64,
-            in("rsi") self.pid,
-            in("rdx") self.data.as_ptr() as u64,
-            in("rcx") self.data.len() as u64,
-            options(nostack),
-        );
+    /// Sends a copy of `data` to `pid`.
+    ///
+    /// The kernel hands the whole area holding a message over to the target and frees it once
+    /// acknowledged, so the copy is placed in a mapping of its own.
+    pub unsafe fn send(pid: u64, data: &[u8]) -> Result<(), SystemCallError> {
+        let len = data.len() as u64;
+        let addr = SystemCall::map(0, len, MapFlags::READ | MapFlags::WRITE)?;
+        core::ptr::copy_nonoverlapping(data.as_ptr(), addr as *mut u8, data.len());
+        let ret = raw::msg_send(pid, addr, len);
+        if ret.is_err() {
+            let _ = SystemCall::unmap(addr, len);
+        }
+        ret
     }
 }
 
@@ -68,14 +55,7 @@ impl Drop for Message {
         if self.id == 0 {
             return;
         }
-        unsafe {
-            core::arch::asm!(
-                "int 249",
-                in("rdi") SystemCall::MsgAck as u64,
-                in("rsi") self.id,
-                options(nostack),
-            );
-        }
+        let _ = unsafe { raw::msg_ack(self.id) };
     }
 }
 
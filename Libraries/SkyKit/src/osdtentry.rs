@@ -11,11 +11,23 @@ use serde::{Deserialize, Serialize};
 
 use crate::osvalue::OSValue;
 #[cfg(feature = "userspace")]
-use crate::syscall::SystemCall;
+use crate::syscall::{raw, SystemCallError};
 
 pub const OSDTENTRY_NAME_KEY: &str = "_Name";
 pub const SKEXT_MATCH_KEY: &str = "_SKExtMatch";
 pub const SKEXT_PROC_KEY: &str = "_SKExtProc";
+pub const SKEXT_ERROR_KEY: &str = "_SKExtError";
+/// Personality key limiting the memory the extension's process may reserve, in bytes. Stacks
+/// only count the pages they use.
+pub const SKEXT_MEMORY_LIMIT_KEY: &str = "_SKExtMemoryLimit";
+/// Personality keys configuring the extension rather than matching entries.
+pub const SKEXT_OPTION_KEYS: [&str; 1] = [SKEXT_MEMORY_LIMIT_KEY];
+
+#[inline]
+#[must_use]
+pub fn is_reserved_key(k: &str) -> bool {
+    k.starts_with('_')
+}
 
 #[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
 #[repr(transparent)]
@@ -36,75 +48,52 @@ pub struct OSDTEntryProp(pub String, pub OSValue);
 
 #[cfg(feature = "userspace")]
 impl OSDTEntry {
-    fn get_info(&self, ty: OSDTEntryInfo, k: Option<&str>) -> Vec<u8> {
-        let (mut ptr, mut len): (u64, u64);
+    fn get_info(&self, ty: OSDTEntryInfo, k: Option<&str>) -> Result<Vec<u8>, SystemCallError> {
         unsafe {
-            core::arch::asm!(
-                "int 249",
-                in("rdi") SystemCall::GetOSDTEntryInfo as u64,
-                in("rsi") self.0,
-                in("rdx") ty as u64,
-                in("rcx") k.map_or(0, |s| s.as_ptr() as u64),
-                in("r8") k.map_or(0, |s| s.len() as u64),
-                out("rax") ptr,
-                lateout("rdi") len,
-                options(nostack),
-            );
-            Vec::from_raw_parts(ptr as *mut u8, len as _, len as _)
+            let ret = raw::get_osdt_entry_info(
+                self.0,
+                ty as u64,
+                k.map_or(0, |s| s.as_ptr() as u64),
+                k.map_or(0, |s| s.len() as u64),
+            )?;
+            Ok(Vec::from_raw_parts(
+                ret.ptr as *mut u8,
+                ret.len as _,
+                ret.len as _,
+            ))
         }
     }
 
-    #[must_use]
-    pub fn new_child(&self, name: Option<&str>) -> Self {
-        let mut id: u64;
+    pub fn new_child(&self, name: Option<&str>) -> Result<Self, SystemCallError> {
         unsafe {
-            core::arch::asm!(
-                "int 249",
-                in("rdi") SystemCall::NewOSDTEntry as u64,
-                in("rsi") self.0,
-                out("rax") id,
-                options(nostack),
-            );
-        }
-        let ret: Self = id.into();
-        if let Some(name) = name {
-            ret.set_property(OSDTENTRY_NAME_KEY, name.into());
+            raw::new_osdt_entry(
+                self.0,
+                name.map_or(0, |s| s.as_ptr() as u64),
+                name.map_or(0, |s| s.len() as u64),
+            )
         }
-        ret
+        .map(|v| v.id.into())
     }
 
-    #[must_use]
-    pub fn parent(&self) -> Option<Self> {
-        postcard::from_bytes(&self.get_info(OSDTEntryInfo::Parent, None)).unwrap()
+    pub fn parent(&self) -> Result<Option<Self>, SystemCallError> {
+        Ok(postcard::from_bytes(&self.get_info(OSDTEntryInfo::Parent, None)?).unwrap())
     }
 
-    #[must_use]
-    pub fn children(&self) -> Vec<Self> {
-        postcard::from_bytes(&self.get_info(OSDTEntryInfo::Children, None)).unwrap()
+    pub fn children(&self) -> Result<Vec<Self>, SystemCallError> {
+        Ok(postcard::from_bytes(&self.get_info(OSDTEntryInfo::Children, None)?).unwrap())
     }
 
-    #[must_use]
-    pub fn properties(&self) -> HashMap<String, OSValue> {
-        postcard::from_bytes(&self.get_info(OSDTEntryInfo::Properties, None)).unwrap()
+    pub fn properties(&self) -> Result<HashMap<String, OSValue>, SystemCallError> {
+        Ok(postcard::from_bytes(&self.get_info(OSDTEntryInfo::Properties, None)?).unwrap())
     }
 
-    #[must_use]
-    pub fn get_property(&self, k: &str) -> Option<OSValue> {
-        postcard::from_bytes(&self.get_info(OSDTEntryInfo::Property, Some(k))).unwrap()
+    pub fn get_property(&self, k: &str) -> Result<Option<OSValue>, SystemCallError> {
+        Ok(postcard::from_bytes(&self.get_info(OSDTEntryInfo::Property, Some(k))?).unwrap())
     }
 
-    pub fn set_property(&self, k: &str, v: OSValue) {
+    pub fn set_property(&self, k: &str, v: OSValue) -> Result<(), SystemCallError> {
         let req = postcard::to_allocvec(&OSDTEntryProp(k.to_owned(), v)).unwrap();
-        unsafe {
-            core::arch::asm!(
-                "int 249",
-                in("rdi") SystemCall::SetOSDTEntryProp as u64,
-                in("rsi") self.0,
-                in("rdx") req.as_ptr() as u64,
-                in("rcx") req.len() as u64,
-                options(nostack),
-            );
-        }
+        unsafe { raw::set_osdt_entry_prop(self.0, req.as_ptr() as u64, req.len() as u64) }
     }
 }
 
@@ -0,0 +1,205 @@
+// Copyright (c) ChefKiss 2021-2025. Licensed under the Thou Shalt Not Profit License version 1.5. See LICENSE for details.
+
+use serde::{
+    de::{
+        self,
+        value::{MapDeserializer, SeqDeserializer},
+        DeserializeOwned, IntoDeserializer, Visitor,
+    },
+    forward_to_deserialize_any,
+};
+
+use super::{OSValue, OSValueError, OSValueType};
+
+pub fn from_value<T: DeserializeOwned>(val: OSValue) -> Result<T, OSValueError> {
+    T::deserialize(val)
+}
+
+impl IntoDeserializer<'_, OSValueError> for OSValue {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for OSValue {
+    type Error = OSValueError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, OSValueError> {
+        match self {
+            Self::Bool(v) => visitor.visit_bool(v),
+            Self::String(v) => visitor.visit_string(v),
+            Self::USize(v) => visitor.visit_u64(v as u64),
+            Self::U64(v) => visitor.visit_u64(v),
+            Self::U32(v) => visitor.visit_u32(v),
+            Self::U16(v) => visitor.visit_u16(v),
+            Self::U8(v) => visitor.visit_u8(v),
+            Self::ISize(v) => visitor.visit_i64(v as i64),
+            Self::I64(v) => visitor.visit_i64(v),
+            Self::I32(v) => visitor.visit_i32(v),
+            Self::I16(v) => visitor.visit_i16(v),
+            Self::I8(v) => visitor.visit_i8(v),
+            Self::Vec(v) => {
+                let mut seq = SeqDeserializer::new(v.into_iter());
+                let ret = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(ret)
+            }
+            Self::Dictionary(v) => {
+                let mut map = MapDeserializer::new(v.into_iter());
+                let ret = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(ret)
+            }
+            Self::Tuple(v) => {
+                let mut seq = SeqDeserializer::new([v.0, v.1].into_iter());
+                let ret = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(ret)
+            }
+            Self::Data(v) => visitor.visit_byte_buf(v),
+            Self::F64(v) => visitor.visit_f64(v),
+            Self::Null => visitor.visit_unit(),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, OSValueError> {
+        match self {
+            Self::Null => visitor.visit_none(),
+            v => visitor.visit_some(v),
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, OSValueError> {
+        match self {
+            Self::Data(v) => {
+                let mut seq = SeqDeserializer::new(v.into_iter().map(Self::U8));
+                let ret = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(ret)
+            }
+            v => v.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, OSValueError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, OSValueError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, OSValueError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, OSValueError> {
+        match self {
+            Self::String(variant) => visitor.visit_enum(EnumDeserializer {
+                variant,
+                value: None,
+            }),
+            Self::Dictionary(v) if v.len() == 1 => {
+                let (variant, value) = v.into_iter().next().unwrap();
+                visitor.visit_enum(EnumDeserializer {
+                    variant,
+                    value: Some(value),
+                })
+            }
+            v => Err(OSValueError::mismatch(OSValueType::Dictionary, &v)),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct map struct identifier ignored_any
+    }
+}
+
+struct EnumDeserializer {
+    variant: alloc::string::String,
+    value: Option<OSValue>,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumDeserializer {
+    type Error = OSValueError;
+    type Variant = VariantDeserializer;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, VariantDeserializer), OSValueError> {
+        let variant = seed.deserialize(OSValue::String(self.variant))?;
+        Ok((variant, VariantDeserializer(self.value)))
+    }
+}
+
+struct VariantDeserializer(Option<OSValue>);
+
+impl<'de> de::VariantAccess<'de> for VariantDeserializer {
+    type Error = OSValueError;
+
+    fn unit_variant(self) -> Result<(), OSValueError> {
+        match self.0 {
+            None | Some(OSValue::Null) => Ok(()),
+            Some(v) => Err(OSValueError::mismatch(OSValueType::Null, &v)),
+        }
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, OSValueError> {
+        seed.deserialize(self.0.unwrap_or(OSValue::Null))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, OSValueError> {
+        match self.0 {
+            Some(v @ (OSValue::Vec(_) | OSValue::Tuple(_))) => {
+                de::Deserializer::deserialize_seq(v, visitor)
+            }
+            v => Err(OSValueError::mismatch(
+                OSValueType::Vec,
+                v.as_ref().unwrap_or(&OSValue::Null),
+            )),
+        }
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, OSValueError> {
+        match self.0 {
+            Some(v @ OSValue::Dictionary(_)) => de::Deserializer::deserialize_any(v, visitor),
+            v => Err(OSValueError::mismatch(
+                OSValueType::Dictionary,
+                v.as_ref().unwrap_or(&OSValue::Null),
+            )),
+        }
+    }
+}
@@ -0,0 +1,352 @@
+// Copyright (c) ChefKiss 2021-2025. Licensed under the Thou Shalt Not Profit License version 1.5. See LICENSE for details.
+
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt;
+
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+
+pub use self::{de::from_value, ser::to_value};
+
+mod de;
+mod ser;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[repr(C)]
+pub enum OSValue {
+    Bool(bool),
+    String(String),
+    USize(usize),
+    U64(u64),
+    U32(u32),
+    U16(u16),
+    U8(u8),
+    ISize(isize),
+    I64(i64),
+    I32(i32),
+    I16(i16),
+    I8(i8),
+    Vec(Vec<Self>),
+    Dictionary(HashMap<String, Self>),
+    Tuple(Box<(Self, Self)>),
+    Data(Vec<u8>),
+    /// Finite once stored in the OSDT, see [`OSValue::is_finite`].
+    F64(f64),
+    Null,
+}
+
+/// Declared in the order of [`OSValue`], so that casting gives the serialised variant index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OSValueType {
+    Bool,
+    String,
+    USize,
+    U64,
+    U32,
+    U16,
+    U8,
+    ISize,
+    I64,
+    I32,
+    I16,
+    I8,
+    Vec,
+    Dictionary,
+    Tuple,
+    Data,
+    F64,
+    Null,
+}
+
+impl OSValue {
+    #[must_use]
+    pub const fn value_type(&self) -> OSValueType {
+        match self {
+            Self::Bool(_) => OSValueType::Bool,
+            Self::String(_) => OSValueType::String,
+            Self::USize(_) => OSValueType::USize,
+            Self::U64(_) => OSValueType::U64,
+            Self::U32(_) => OSValueType::U32,
+            Self::U16(_) => OSValueType::U16,
+            Self::U8(_) => OSValueType::U8,
+            Self::ISize(_) => OSValueType::ISize,
+            Self::I64(_) => OSValueType::I64,
+            Self::I32(_) => OSValueType::I32,
+            Self::I16(_) => OSValueType::I16,
+            Self::I8(_) => OSValueType::I8,
+            Self::Vec(_) => OSValueType::Vec,
+            Self::Dictionary(_) => OSValueType::Dictionary,
+            Self::Tuple(_) => OSValueType::Tuple,
+            Self::Data(_) => OSValueType::Data,
+            Self::F64(_) => OSValueType::F64,
+            Self::Null => OSValueType::Null,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn is_null(&self) -> bool {
+        matches!(self, Self::Null)
+    }
+
+    /// Whether every [`OSValue::F64`] within is finite.
+    ///
+    /// The OSDT only accepts finite values, as a NaN never equals itself and so would never
+    /// match a personality, and snapshots cannot represent NaN or infinities in JSON.
+    #[must_use]
+    pub fn is_finite(&self) -> bool {
+        match self {
+            Self::F64(v) => v.is_finite(),
+            Self::Vec(v) => v.iter().all(Self::is_finite),
+            Self::Dictionary(v) => v.values().all(Self::is_finite),
+            Self::Tuple(v) => v.0.is_finite() && v.1.is_finite(),
+            _ => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OSValueError {
+    TypeMismatch {
+        expected: OSValueType,
+        found: OSValueType,
+    },
+    Custom(String),
+}
+
+impl OSValueError {
+    #[inline]
+    #[must_use]
+    pub const fn mismatch(expected: OSValueType, found: &OSValue) -> Self {
+        Self::TypeMismatch {
+            expected,
+            found: found.value_type(),
+        }
+    }
+}
+
+impl fmt::Display for OSValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TypeMismatch { expected, found } => {
+                write!(f, "Expected {expected:?}, found {found:?}")
+            }
+            Self::Custom(v) => f.write_str(v),
+        }
+    }
+}
+
+impl core::error::Error for OSValueError {}
+
+impl serde::ser::Error for OSValueError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self::Custom(msg.to_string())
+    }
+}
+
+impl serde::de::Error for OSValueError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self::Custom(msg.to_string())
+    }
+}
+
+macro_rules! OSValueImplFor {
+    ($variant:ident, $target:ty) => {
+        impl From<$target> for OSValue {
+            fn from(val: $target) -> Self {
+                Self::$variant(val)
+            }
+        }
+
+        impl TryFrom<OSValue> for $target {
+            type Error = OSValueError;
+
+            fn try_from(val: OSValue) -> Result<Self, Self::Error> {
+                match val {
+                    OSValue::$variant(d) => Ok(d),
+                    v => Err(OSValueError::mismatch(OSValueType::$variant, &v)),
+                }
+            }
+        }
+
+        impl<'a> TryFrom<&'a OSValue> for &'a $target {
+            type Error = OSValueError;
+
+            fn try_from(val: &'a OSValue) -> Result<Self, Self::Error> {
+                match val {
+                    OSValue::$variant(d) => Ok(d),
+                    v => Err(OSValueError::mismatch(OSValueType::$variant, v)),
+                }
+            }
+        }
+    };
+}
+
+OSValueImplFor!(Bool, bool);
+OSValueImplFor!(String, String);
+impl From<&str> for OSValue {
+    fn from(val: &str) -> Self {
+        Self::String(val.into())
+    }
+}
+impl<'a> TryFrom<&'a OSValue> for &'a str {
+    type Error = OSValueError;
+
+    fn try_from(val: &'a OSValue) -> Result<Self, Self::Error> {
+        match val {
+            OSValue::String(d) => Ok(d.as_str()),
+            v => Err(OSValueError::mismatch(OSValueType::String, v)),
+        }
+    }
+}
+OSValueImplFor!(USize, usize);
+OSValueImplFor!(U64, u64);
+OSValueImplFor!(U32, u32);
+OSValueImplFor!(U16, u16);
+OSValueImplFor!(U8, u8);
+OSValueImplFor!(ISize, isize);
+OSValueImplFor!(I64, i64);
+OSValueImplFor!(I32, i32);
+OSValueImplFor!(I16, i16);
+OSValueImplFor!(I8, i8);
+OSValueImplFor!(Vec, Vec<OSValue>);
+OSValueImplFor!(Dictionary, HashMap<String, OSValue>);
+OSValueImplFor!(Data, Vec<u8>);
+impl From<&[u8]> for OSValue {
+    fn from(val: &[u8]) -> Self {
+        Self::Data(val.into())
+    }
+}
+impl<'a> TryFrom<&'a OSValue> for &'a [u8] {
+    type Error = OSValueError;
+
+    fn try_from(val: &'a OSValue) -> Result<Self, Self::Error> {
+        match val {
+            OSValue::Data(d) => Ok(d.as_slice()),
+            v => Err(OSValueError::mismatch(OSValueType::Data, v)),
+        }
+    }
+}
+OSValueImplFor!(F64, f64);
+impl<T: Into<Self>> From<Option<T>> for OSValue {
+    fn from(val: Option<T>) -> Self {
+        val.map_or(Self::Null, Into::into)
+    }
+}
+impl<A: Into<Self>, B: Into<Self>> From<(A, B)> for OSValue {
+    fn from(val: (A, B)) -> Self {
+        Self::Tuple((val.0.into(), val.1.into()).into())
+    }
+}
+impl<
+        'a,
+        A: TryFrom<&'a OSValue, Error = OSValueError>,
+        B: TryFrom<&'a OSValue, Error = OSValueError>,
+    > TryFrom<&'a OSValue> for (A, B)
+{
+    type Error = OSValueError;
+
+    fn try_from(val: &'a OSValue) -> Result<Self, Self::Error> {
+        match val {
+            OSValue::Tuple(v) => Ok(((&v.0).try_into()?, (&v.1).try_into()?)),
+            v => Err(OSValueError::mismatch(OSValueType::Tuple, v)),
+        }
+    }
+}
+
+// `{}` prints the value on a single line, `{:#}` spreads containers over indented lines.
+// Dictionary keys are printed in sorted order.
+impl OSValue {
+    fn write_pretty(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        let (pretty, indent) = (f.alternate(), "    ");
+        let items = |f: &mut fmt::Formatter<'_>,
+                     items: &mut dyn Iterator<Item = (Option<&str>, &Self)>|
+         -> fmt::Result {
+            let mut first = true;
+            for (k, v) in items {
+                if pretty {
+                    f.write_str("\n")?;
+                    for _ in 0..=depth {
+                        f.write_str(indent)?;
+                    }
+                } else if !first {
+                    f.write_str(", ")?;
+                }
+                first = false;
+                if let Some(k) = k {
+                    write!(f, "{k:?}: ")?;
+                }
+                v.write_pretty(f, depth + 1)?;
+                if pretty {
+                    f.write_str(",")?;
+                }
+            }
+            if pretty && !first {
+                f.write_str("\n")?;
+                for _ in 0..depth {
+                    f.write_str(indent)?;
+                }
+            }
+            Ok(())
+        };
+
+        match self {
+            Self::Bool(v) => write!(f, "{v}"),
+            Self::String(v) => write!(f, "{v:?}"),
+            Self::USize(v) => write!(f, "{v}"),
+            Self::U64(v) => write!(f, "{v}"),
+            Self::U32(v) => write!(f, "{v}"),
+            Self::U16(v) => write!(f, "{v}"),
+            Self::U8(v) => write!(f, "{v}"),
+            Self::ISize(v) => write!(f, "{v}"),
+            Self::I64(v) => write!(f, "{v}"),
+            Self::I32(v) => write!(f, "{v}"),
+            Self::I16(v) => write!(f, "{v}"),
+            Self::I8(v) => write!(f, "{v}"),
+            Self::Vec(v) => {
+                f.write_str("[")?;
+                items(f, &mut v.iter().map(|v| (None, v)))?;
+                f.write_str("]")
+            }
+            Self::Dictionary(v) => {
+                let mut sorted: Vec<_> = v.iter().collect();
+                sorted.sort_unstable_by(|a, b| a.0.cmp(b.0));
+                f.write_str("{")?;
+                items(
+                    f,
+                    &mut sorted.into_iter().map(|(k, v)| (Some(k.as_str()), v)),
+                )?;
+                f.write_str("}")
+            }
+            Self::Tuple(v) => {
+                f.write_str("(")?;
+                v.0.write_pretty(f, depth)?;
+                f.write_str(", ")?;
+                v.1.write_pretty(f, depth)?;
+                f.write_str(")")
+            }
+            Self::Data(v) => {
+                f.write_str("<")?;
+                for (i, b) in v.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(" ")?;
+                    }
+                    write!(f, "{b:02X}")?;
+                }
+                f.write_str(">")
+            }
+            Self::F64(v) => write!(f, "{v:?}"),
+            Self::Null => f.write_str("null"),
+        }
+    }
+}
+
+impl fmt::Display for OSValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_pretty(f, 0)
+    }
+}
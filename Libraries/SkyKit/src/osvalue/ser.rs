@@ -0,0 +1,346 @@
+// Copyright (c) ChefKiss 2021-2025. Licensed under the Thou Shalt Not Profit License version 1.5. See LICENSE for details.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use hashbrown::HashMap;
+use serde::{ser, Serialize};
+
+use super::{OSValue, OSValueError};
+
+pub fn to_value<T: Serialize + ?Sized>(val: &T) -> Result<OSValue, OSValueError> {
+    val.serialize(Serializer)
+}
+
+struct Serializer;
+
+fn variant(name: &'static str, val: OSValue) -> OSValue {
+    OSValue::Dictionary(HashMap::from([(name.into(), val)]))
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = OSValue;
+    type Error = OSValueError;
+    type SerializeSeq = SerializeVec;
+    type SerializeTuple = SerializeVec;
+    type SerializeTupleStruct = SerializeVec;
+    type SerializeTupleVariant = SerializeVec;
+    type SerializeMap = SerializeDictionary;
+    type SerializeStruct = SerializeDictionary;
+    type SerializeStructVariant = SerializeDictionary;
+
+    fn serialize_bool(self, v: bool) -> Result<OSValue, OSValueError> {
+        Ok(v.into())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<OSValue, OSValueError> {
+        Ok(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<OSValue, OSValueError> {
+        Ok(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<OSValue, OSValueError> {
+        Ok(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<OSValue, OSValueError> {
+        Ok(v.into())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<OSValue, OSValueError> {
+        Ok(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<OSValue, OSValueError> {
+        Ok(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<OSValue, OSValueError> {
+        Ok(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<OSValue, OSValueError> {
+        Ok(v.into())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<OSValue, OSValueError> {
+        Ok(f64::from(v).into())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<OSValue, OSValueError> {
+        Ok(v.into())
+    }
+
+    fn serialize_char(self, v: char) -> Result<OSValue, OSValueError> {
+        Ok(v.to_string().into())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<OSValue, OSValueError> {
+        Ok(v.into())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<OSValue, OSValueError> {
+        Ok(v.into())
+    }
+
+    fn serialize_none(self) -> Result<OSValue, OSValueError> {
+        Ok(OSValue::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<OSValue, OSValueError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<OSValue, OSValueError> {
+        Ok(OSValue::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<OSValue, OSValueError> {
+        Ok(OSValue::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<OSValue, OSValueError> {
+        Ok(variant.into())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<OSValue, OSValueError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<OSValue, OSValueError> {
+        Ok(self::variant(variant, value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeVec, OSValueError> {
+        Ok(SerializeVec {
+            variant: None,
+            vec: Vec::with_capacity(len.unwrap_or_default()),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeVec, OSValueError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeVec, OSValueError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeVec, OSValueError> {
+        Ok(SerializeVec {
+            variant: Some(variant),
+            vec: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<SerializeDictionary, OSValueError> {
+        Ok(SerializeDictionary {
+            variant: None,
+            dict: HashMap::with_capacity(len.unwrap_or_default()),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeDictionary, OSValueError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeDictionary, OSValueError> {
+        Ok(SerializeDictionary {
+            variant: Some(variant),
+            dict: HashMap::with_capacity(len),
+            key: None,
+        })
+    }
+}
+
+struct SerializeVec {
+    variant: Option<&'static str>,
+    vec: Vec<OSValue>,
+}
+
+impl SerializeVec {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), OSValueError> {
+        self.vec.push(value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> OSValue {
+        let vec = OSValue::Vec(self.vec);
+        match self.variant {
+            Some(name) => variant(name, vec),
+            None => vec,
+        }
+    }
+}
+
+impl ser::SerializeSeq for SerializeVec {
+    type Ok = OSValue;
+    type Error = OSValueError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), OSValueError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<OSValue, OSValueError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTuple for SerializeVec {
+    type Ok = OSValue;
+    type Error = OSValueError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), OSValueError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<OSValue, OSValueError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeVec {
+    type Ok = OSValue;
+    type Error = OSValueError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), OSValueError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<OSValue, OSValueError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeVec {
+    type Ok = OSValue;
+    type Error = OSValueError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), OSValueError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<OSValue, OSValueError> {
+        Ok(self.finish())
+    }
+}
+
+struct SerializeDictionary {
+    variant: Option<&'static str>,
+    dict: HashMap<String, OSValue>,
+    key: Option<String>,
+}
+
+impl SerializeDictionary {
+    fn finish(self) -> OSValue {
+        let dict = OSValue::Dictionary(self.dict);
+        match self.variant {
+            Some(name) => variant(name, dict),
+            None => dict,
+        }
+    }
+}
+
+impl ser::SerializeMap for SerializeDictionary {
+    type Ok = OSValue;
+    type Error = OSValueError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), OSValueError> {
+        match key.serialize(Serializer)? {
+            OSValue::String(v) => {
+                self.key = Some(v);
+                Ok(())
+            }
+            v => Err(OSValueError::mismatch(super::OSValueType::String, &v)),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), OSValueError> {
+        let key = self
+            .key
+            .take()
+            .expect("serialize_value called before serialize_key");
+        self.dict.insert(key, value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<OSValue, OSValueError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeStruct for SerializeDictionary {
+    type Ok = OSValue;
+    type Error = OSValueError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), OSValueError> {
+        self.dict.insert(key.into(), value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<OSValue, OSValueError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeDictionary {
+    type Ok = OSValue;
+    type Error = OSValueError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), OSValueError> {
+        self.dict.insert(key.into(), value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<OSValue, OSValueError> {
+        Ok(self.finish())
+    }
+}
@@ -0,0 +1,60 @@
+// Copyright (c) ChefKiss 2021-2025. Licensed under the Thou Shalt Not Profit License version 1.5. See LICENSE for details.
+
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+
+use serde::{ser::Error, Deserialize, Serialize, Serializer};
+
+use crate::osvalue::OSValue;
+
+pub const SNAPSHOT_BEGIN_MARKER: &str = "-----BEGIN OSDT SNAPSHOT-----";
+pub const SNAPSHOT_END_MARKER: &str = "-----END OSDT SNAPSHOT-----";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OSDTSnapshot {
+    pub id: u64,
+    #[serde(serialize_with = "serialize_sorted")]
+    pub properties: BTreeMap<String, OSValue>,
+    pub children: Vec<Self>,
+}
+
+// Serialises dictionaries in key order so that snapshots of the same tree are byte-identical.
+pub struct Canonical<'a>(pub &'a OSValue);
+
+impl Serialize for Canonical<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let index = self.0.value_type() as u32;
+        match self.0 {
+            OSValue::Vec(v) => serializer.serialize_newtype_variant(
+                "OSValue",
+                index,
+                "Vec",
+                &v.iter().map(Canonical).collect::<Vec<_>>(),
+            ),
+            OSValue::Dictionary(v) => serializer.serialize_newtype_variant(
+                "OSValue",
+                index,
+                "Dictionary",
+                &v.iter()
+                    .map(|(k, v)| (k, Canonical(v)))
+                    .collect::<BTreeMap<_, _>>(),
+            ),
+            OSValue::Tuple(v) => serializer.serialize_newtype_variant(
+                "OSValue",
+                index,
+                "Tuple",
+                &(Canonical(&v.0), Canonical(&v.1)),
+            ),
+            OSValue::F64(v) if !v.is_finite() => Err(S::Error::custom(format_args!(
+                "{v} cannot be represented in a snapshot"
+            ))),
+            v => v.serialize(serializer),
+        }
+    }
+}
+
+fn serialize_sorted<S: Serializer>(
+    properties: &BTreeMap<String, OSValue>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(properties.iter().map(|(k, v)| (k, Canonical(v))))
+}
@@ -1,6 +1,44 @@
 // Copyright (c) ChefKiss 2021-2025. Licensed under the Thou Shalt Not Profit License version 1.5. See LICENSE for details.
 
 use num_enum::TryFromPrimitive;
+use serde::{Deserialize, Serialize};
+
+/// Bump whenever the system call table, its register assignments or the errors it returns
+/// change.
+pub const ABI_VERSION: u32 = 3;
+pub const ABI_NOTE_NAME: &str = "SkyKit";
+pub const NT_SKYKIT_ABI: u64 = 1;
+
+/// ELF note carrying the [`ABI_VERSION`] a binary was built against.
+///
+/// The kernel refuses to spawn executables without a matching one.
+#[repr(C, align(4))]
+pub struct ABINote {
+    namesz: u32,
+    descsz: u32,
+    ty: u32,
+    name: [u8; 8],
+    version: u32,
+}
+
+impl ABINote {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            namesz: ABI_NOTE_NAME.len() as u32 + 1,
+            descsz: 4,
+            ty: NT_SKYKIT_ABI as u32,
+            name: *b"SkyKit\0\0",
+            version: ABI_VERSION,
+        }
+    }
+}
+
+impl Default for ABINote {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromPrimitive)]
 #[repr(u64)]
@@ -10,41 +48,219 @@ pub enum AccessSize {
     DWord,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromPrimitive)]
+/// Access and placement of a mapping.
+///
+/// A mapping with none of the access flags is a guard region that faults on any access.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct MapFlags(pub u64);
+
+impl MapFlags {
+    pub const READ: Self = Self(1 << 0);
+    pub const WRITE: Self = Self(1 << 1);
+    pub const EXECUTE: Self = Self(1 << 2);
+    /// Map at exactly the given address instead of anywhere.
+    pub const FIXED: Self = Self(1 << 3);
+    pub const ALL: Self = Self(0b1111);
+
+    #[inline]
+    #[must_use]
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl core::ops::BitOr for MapFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// Arguments or results of a system call as they are laid out in registers.
+///
+/// The call number goes in `rdi` and arguments in `rsi`, `rdx`, `r10` and `r8`.
+/// The status is returned in `rax` and results in `rdi`, `rsi`, `rdx` and `r10`, in that order.
+/// `syscall` clobbers `rcx` and `r11`; `int 249` follows the same convention.
+pub trait SystemCallRegisters: Sized {
+    fn from_registers(regs: [u64; 4]) -> Self;
+    fn into_registers(self) -> [u64; 4];
+}
+
+impl SystemCallRegisters for () {
+    fn from_registers(_: [u64; 4]) -> Self {}
+
+    fn into_registers(self) -> [u64; 4] {
+        [0; 4]
+    }
+}
+
+#[inline]
+#[must_use]
+pub fn pack_registers(vals: &[u64]) -> [u64; 4] {
+    let mut regs = [0; 4];
+    regs[..vals.len()].copy_from_slice(vals);
+    regs
+}
+
+// Every row generates the call number, the argument and result structs in `args` and `rets`
+// (`()` when there are none) and, for extensions, a stub in `raw`.
+macro_rules! system_calls {
+    (@ty $module:ident $name:ident) => { () };
+    (@ty $module:ident $name:ident $($field:ident)+) => { $module::$name };
+    (@struct $name:ident) => {};
+    (@struct $name:ident $($field:ident)+) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct $name {
+            $(pub $field: u64,)+
+        }
+
+        impl $crate::syscall::SystemCallRegisters for $name {
+            fn from_registers(regs: [u64; 4]) -> Self {
+                let [$($field,)+ ..] = regs;
+                Self { $($field,)+ }
+            }
+
+            fn into_registers(self) -> [u64; 4] {
+                $crate::syscall::pack_registers(&[$(self.$field,)+])
+            }
+        }
+    };
+    ($($name:ident = $fn:ident($($arg:ident),*) $(-> { $($ret:ident),+ })?;)+) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromPrimitive)]
+        #[repr(u64)]
+        pub enum SystemCall {
+            $($name,)+
+        }
+
+        pub mod args {
+            $(system_calls!(@struct $name $($arg)*);)+
+        }
+
+        pub mod rets {
+            $(system_calls!(@struct $name $($($ret)+)?);)+
+        }
+
+        #[cfg(feature = "userspace")]
+        pub mod raw {
+            use super::{rets, SystemCall, SystemCallError, SystemCallRegisters};
+
+            #[inline(always)]
+            unsafe fn invoke(call: SystemCall, args: [u64; 4]) -> Result<[u64; 4], SystemCallError> {
+                let (status, a, b, c, d): (u64, u64, u64, u64, u64);
+                core::arch::asm!(
+                    "syscall",
+                    inout("rdi") call as u64 => a,
+                    inout("rsi") args[0] => b,
+                    inout("rdx") args[1] => c,
+                    inout("r10") args[2] => d,
+                    in("r8") args[3],
+                    lateout("rax") status,
+                    lateout("rcx") _,
+                    lateout("r11") _,
+                    options(nostack),
+                );
+                SystemCallError::from_status(status).map(|()| [a, b, c, d])
+            }
+
+            $(
+                #[inline]
+                pub unsafe fn $fn(
+                    $($arg: u64),*
+                ) -> Result<system_calls!(@ty rets $name $($($ret)+)?), SystemCallError> {
+                    invoke(SystemCall::$name, super::pack_registers(&[$($arg),*]))
+                        .map(SystemCallRegisters::from_registers)
+                }
+            )+
+        }
+    };
+}
+
+system_calls! {
+    KPrint = kprint(ptr, len);
+    MsgRecv = msg_recv() -> { id, pid, ptr, len };
+    MsgSend = msg_send(pid, ptr, len);
+    Quit = quit();
+    Yield = r#yield();
+    PortIn = port_in(port, size) -> { value };
+    PortOut = port_out(port, size, value);
+    RegisterIRQ = register_irq(irq);
+    Allocate = allocate(size) -> { addr };
+    Free = free(addr, size);
+    MsgAck = msg_ack(id);
+    NewOSDTEntry = new_osdt_entry(parent, name_ptr, name_len) -> { id };
+    GetOSDTEntryInfo = get_osdt_entry_info(id, ty, key_ptr, key_len) -> { ptr, len };
+    SetOSDTEntryProp = set_osdt_entry_prop(id, ptr, len);
+    OSDTSnapshot = osdt_snapshot();
+    Map = map(addr, len, flags) -> { addr };
+    Unmap = unmap(addr, len);
+    Protect = protect(addr, len, flags);
+}
+
+/// Status returned by every system call in `rax`; zero means success.
+///
+/// Malformed pointers are not recoverable and terminate the caller instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromPrimitive, Serialize, Deserialize)]
 #[repr(u64)]
-pub enum SystemCall {
-    KPrint,
-    MsgRecv,
-    MsgSend,
-    Quit,
-    Yield,
-    PortIn,
-    PortOut,
-    RegisterIRQ,
-    Allocate,
-    Free,
-    MsgAck,
-    NewOSDTEntry,
-    GetOSDTEntryInfo,
-    SetOSDTEntryProp,
+pub enum SystemCallError {
+    Unspecified = 1,
+    UnknownSystemCall,
+    MalformedArgument,
+    MalformedBody,
+    NotFound,
+    AlreadyExists,
+    InsufficientPermissions,
+    OutOfMemory,
+    /// The caller would go over its memory limit.
+    LimitExceeded,
+}
+
+impl SystemCallError {
+    #[inline]
+    pub fn from_status(status: u64) -> Result<(), Self> {
+        if status == 0 {
+            Ok(())
+        } else {
+            Err(Self::try_from(status).unwrap_or(Self::Unspecified))
+        }
+    }
+}
+
+impl From<SystemCallError> for u64 {
+    fn from(val: SystemCallError) -> Self {
+        val as Self
+    }
 }
 
 #[cfg(feature = "userspace")]
 impl SystemCall {
     pub unsafe fn quit() -> ! {
-        core::arch::asm!("int 249", in("rdi") Self::Quit as u64, options(nostack, noreturn));
+        core::arch::asm!("syscall", in("rdi") Self::Quit as u64, options(nostack, noreturn));
     }
 
     pub unsafe fn r#yield() {
-        core::arch::asm!("int 249", in("rdi") Self::Yield as u64, options(nostack));
+        let _ = raw::r#yield();
+    }
+
+    pub unsafe fn osdt_snapshot() {
+        let _ = raw::osdt_snapshot();
+    }
+
+    pub unsafe fn register_irq_handler(irq: u8) -> Result<(), SystemCallError> {
+        raw::register_irq(irq.into())
+    }
+
+    /// Zero-filled memory at `addr` if `flags` contains [`MapFlags::FIXED`], otherwise anywhere.
+    pub unsafe fn map(addr: u64, len: u64, flags: MapFlags) -> Result<u64, SystemCallError> {
+        raw::map(addr, len, flags.0).map(|v| v.addr)
+    }
+
+    pub unsafe fn unmap(addr: u64, len: u64) -> Result<(), SystemCallError> {
+        raw::unmap(addr, len)
     }
 
-    pub unsafe fn register_irq_handler(irq: u8) {
-        core::arch::asm!(
-            "int 249",
-            in("rdi") Self::RegisterIRQ as u64,
-            in("sil") irq,
-            options(nostack),
-        );
+    pub unsafe fn protect(addr: u64, len: u64, flags: MapFlags) -> Result<(), SystemCallError> {
+        raw::protect(addr, len, flags.0)
     }
 }
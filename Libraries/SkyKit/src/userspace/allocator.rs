@@ -1,37 +1,179 @@
 // Copyright (c) ChefKiss 2021-2025. Licensed under the Thou Shalt Not Profit License version 1.5. See LICENSE for details.
 
-use crate::syscall::SystemCall;
+use crate::syscall::{MapFlags, SystemCall};
 
 #[global_allocator]
-static GLOBAL_ALLOCATOR: Allocator = Allocator;
+static GLOBAL_ALLOCATOR: Allocator = Allocator(spin::Mutex::new(Heap::new()));
 
-struct Allocator;
+const PAGE_SIZE: usize = 0x1000;
+/// Size and alignment of the arenas small objects are carved from.
+const ARENA_LEN: usize = 0x1_0000;
+const SIZE_CLASSES: [usize; 8] = [16, 32, 64, 128, 256, 512, 1024, 2048];
+
+/// Header at the start of every arena, which only holds objects of one size class.
+#[repr(C)]
+struct Arena {
+    class: usize,
+    used: usize,
+    /// Freed objects, each holding the address of the next one.
+    free: usize,
+    /// Offset of the first object that was never handed out, keeping untouched pages unbacked.
+    bump: usize,
+    next: usize,
+    prev: usize,
+}
+
+impl Arena {
+    #[inline]
+    const fn is_full(&self) -> bool {
+        self.free == 0 && self.bump + SIZE_CLASSES[self.class] > ARENA_LEN
+    }
+}
+
+struct Heap {
+    /// Arenas with room left per size class.
+    partial: [usize; SIZE_CLASSES.len()],
+}
+
+fn map(len: usize) -> usize {
+    unsafe { SystemCall::map(0, len as u64, MapFlags::READ | MapFlags::WRITE) }
+        .map_or(0, |v| v as usize)
+}
+
+fn unmap(addr: usize, len: usize) {
+    if len != 0 {
+        unsafe { SystemCall::unmap(addr as u64, len as u64) }.unwrap();
+    }
+}
+
+/// Maps `len` bytes aligned to `align`, trimming the excess of a larger mapping.
+fn map_aligned(len: usize, align: usize) -> usize {
+    let len = len.next_multiple_of(PAGE_SIZE);
+    if align <= PAGE_SIZE {
+        return map(len);
+    }
+    let base = map(len + align);
+    if base == 0 {
+        return 0;
+    }
+    let addr = base.next_multiple_of(align);
+    unmap(base, addr - base);
+    unmap(addr + len, base + align - addr);
+    addr
+}
+
+impl Heap {
+    const fn new() -> Self {
+        Self {
+            partial: [0; SIZE_CLASSES.len()],
+        }
+    }
+
+    fn size_class(layout: core::alloc::Layout) -> Option<usize> {
+        let size = layout.size().max(layout.align());
+        SIZE_CLASSES.iter().position(|&v| v >= size)
+    }
+
+    unsafe fn link(&mut self, arena: *mut Arena) {
+        let class = (*arena).class;
+        (*arena).prev = 0;
+        (*arena).next = self.partial[class];
+        if let Some(next) = ((*arena).next as *mut Arena).as_mut() {
+            next.prev = arena as usize;
+        }
+        self.partial[class] = arena as usize;
+    }
+
+    unsafe fn unlink(&mut self, arena: *mut Arena) {
+        let Arena { next, prev, .. } = *arena;
+        if let Some(prev) = (prev as *mut Arena).as_mut() {
+            prev.next = next;
+        } else {
+            self.partial[(*arena).class] = next;
+        }
+        if let Some(next) = (next as *mut Arena).as_mut() {
+            next.prev = prev;
+        }
+    }
+
+    unsafe fn alloc_small(&mut self, class: usize) -> *mut u8 {
+        if self.partial[class] == 0 {
+            let arena = map_aligned(ARENA_LEN, ARENA_LEN) as *mut Arena;
+            if arena.is_null() {
+                return core::ptr::null_mut();
+            }
+            arena.write(Arena {
+                class,
+                used: 0,
+                free: 0,
+                bump: size_of::<Arena>().next_multiple_of(SIZE_CLASSES[class]),
+                next: 0,
+                prev: 0,
+            });
+            self.link(arena);
+        }
+
+        let arena = &mut *(self.partial[class] as *mut Arena);
+        let obj = if arena.free == 0 {
+            let obj = (arena as *mut Arena as usize + arena.bump) as *mut u8;
+            arena.bump += SIZE_CLASSES[class];
+            obj
+        } else {
+            let obj = arena.free as *mut u8;
+            arena.free = obj.cast::<usize>().read();
+            obj
+        };
+        arena.used += 1;
+        if arena.is_full() {
+            self.unlink(arena);
+        }
+        obj
+    }
+
+    unsafe fn free_small(&mut self, ptr: *mut u8) {
+        let arena = ptr.map_addr(|v| v & !(ARENA_LEN - 1)).cast::<Arena>();
+        let was_full = (*arena).is_full();
+        ptr.cast::<usize>().write((*arena).free);
+        (*arena).free = ptr as usize;
+        (*arena).used -= 1;
+        if was_full {
+            self.link(arena);
+        }
+
+        // Give idle arenas back, keeping one per size class around for the next allocation.
+        let class = (*arena).class;
+        if (*arena).used == 0 && (self.partial[class] != arena as usize || (*arena).next != 0) {
+            self.unlink(arena);
+            unmap(arena as usize, ARENA_LEN);
+        }
+    }
+}
+
+struct Allocator(spin::Mutex<Heap>);
 
 unsafe impl core::alloc::GlobalAlloc for Allocator {
     unsafe fn alloc(&self, layout: core::alloc::Layout) -> *mut u8 {
-        let mut ptr: u64;
-        core::arch::asm!(
-            "int 249",
-            in("rdi") SystemCall::Allocate as u64,
-            in("rsi") layout.pad_to_align().size() as u64,
-            out("rax") ptr,
-            options(nostack),
-        );
-        ptr as *mut u8
+        if let Some(class) = Heap::size_class(layout) {
+            return self.0.lock().alloc_small(class);
+        }
+        map_aligned(layout.size(), layout.align()) as *mut u8
     }
 
     unsafe fn alloc_zeroed(&self, layout: core::alloc::Layout) -> *mut u8 {
-        self.alloc(layout) // Memory is already zeroed by the allocator
+        let ptr = self.alloc(layout);
+        // Large allocations are fresh mappings, which are zeroed by the kernel.
+        if !ptr.is_null() && Heap::size_class(layout).is_some() {
+            ptr.write_bytes(0, layout.size());
+        }
+        ptr
     }
 
     unsafe fn dealloc(&self, ptr: *mut u8, layout: core::alloc::Layout) {
-        core::arch::asm!(
-            "int 249",
-            in("rdi") SystemCall::Free as u64,
-            in("rsi") ptr as u64,
-            in("rdx") layout.pad_to_align().size() as u64,
-            options(nostack),
-        );
+        if Heap::size_class(layout).is_some() {
+            self.0.lock().free_small(ptr);
+        } else {
+            unmap(ptr as usize, layout.size());
+        }
     }
 }
 
@@ -2,7 +2,7 @@
 
 use core::fmt::Write;
 
-use crate::syscall::SystemCall;
+use crate::syscall::raw;
 
 pub struct KWriter;
 
@@ -12,16 +12,7 @@ impl Write for KWriter {
             return Ok(());
         }
 
-        unsafe {
-            core::arch::asm!(
-                "int 249",
-                in("rdi") SystemCall::KPrint as u64,
-                in("rsi") s.as_ptr() as u64,
-                in("rdx") s.len() as u64,
-                options(nostack),
-            );
-        }
-        Ok(())
+        unsafe { raw::kprint(s.as_ptr() as u64, s.len() as u64) }.map_err(|_| core::fmt::Error)
     }
 }
 
@@ -4,3 +4,7 @@ mod allocator;
 pub mod logger;
 mod panic;
 pub mod port;
+
+#[used]
+#[link_section = ".note.skykit.abi"]
+static ABI_NOTE: crate::syscall::ABINote = crate::syscall::ABINote::new();
@@ -1,34 +1,6 @@
 // Copyright (c) ChefKiss 2021-2025. Licensed under the Thou Shalt Not Profit License version 1.5. See LICENSE for details.
 
-use crate::syscall::{AccessSize, SystemCall};
-
-macro_rules! PortIOSystemCallIn {
-    ($out:tt, $port:expr, $size:expr) => {{
-        let mut val: Self;
-        core::arch::asm!(
-            "int 249",
-            in("rdi") SystemCall::PortIn as u64,
-            in("rsi") $port,
-            in("rdx") $size as u64,
-            out($out) val,
-            options(nostack),
-        );
-        val
-    }};
-}
-
-macro_rules! PortIOSystemCallOut {
-    ($in_:tt, $port:expr, $value:expr, $size:expr) => {
-        core::arch::asm!(
-            "int 249",
-            in("rdi") SystemCall::PortOut as u64,
-            in("rsi") $port,
-            in("rdx") $size as u64,
-            in($in_) $value,
-            options(nostack),
-        )
-    };
-}
+use crate::syscall::{raw, AccessSize};
 
 pub trait PortIO: Sized {
     unsafe fn read(port: u16) -> Self;
@@ -37,31 +9,37 @@ pub trait PortIO: Sized {
 
 impl PortIO for u8 {
     unsafe fn read(port: u16) -> Self {
-        PortIOSystemCallIn!("al", port, AccessSize::Byte)
+        raw::port_in(port.into(), AccessSize::Byte as u64)
+            .unwrap()
+            .value as Self
     }
 
     unsafe fn write(port: u16, value: Self) {
-        PortIOSystemCallOut!("cl", port, value, AccessSize::Byte);
+        raw::port_out(port.into(), AccessSize::Byte as u64, value.into()).unwrap();
     }
 }
 
 impl PortIO for u16 {
     unsafe fn read(port: u16) -> Self {
-        PortIOSystemCallIn!("ax", port, AccessSize::Word)
+        raw::port_in(port.into(), AccessSize::Word as u64)
+            .unwrap()
+            .value as Self
     }
 
     unsafe fn write(port: u16, value: Self) {
-        PortIOSystemCallOut!("cx", port, value, AccessSize::Word);
+        raw::port_out(port.into(), AccessSize::Word as u64, value.into()).unwrap();
     }
 }
 
 impl PortIO for u32 {
     unsafe fn read(port: u16) -> Self {
-        PortIOSystemCallIn!("eax", port, AccessSize::DWord)
+        raw::port_in(port.into(), AccessSize::DWord as u64)
+            .unwrap()
+            .value as Self
     }
 
     unsafe fn write(port: u16, value: Self) {
-        PortIOSystemCallOut!("ecx", port, value, AccessSize::DWord);
+        raw::port_out(port.into(), AccessSize::DWord as u64, value.into()).unwrap();
     }
 }
 
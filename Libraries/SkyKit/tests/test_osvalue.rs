@@ -0,0 +1,257 @@
+// Copyright (c) ChefKiss 2021-2025. Licensed under the Thou Shalt Not Profit License version 1.5. See LICENSE for details.
+
+#![deny(warnings, clippy::nursery, unused_extern_crates)]
+
+use std::collections::BTreeMap;
+
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+use skykit::{
+    osvalue::{from_value, to_value, OSValue, OSValueError, OSValueType},
+    snapshot::Canonical,
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum Event {
+    Unit,
+    Newtype(u32),
+    Tuple(u8, String),
+    Struct { id: u64, name: Option<String> },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Device {
+    name: String,
+    ratio: f64,
+    parent: Option<u64>,
+    nothing: (),
+    offset: i16,
+    tags: Vec<String>,
+    pair: (bool, char),
+    events: Vec<Event>,
+    children: HashMap<String, Vec<Self>>,
+}
+
+fn device() -> Device {
+    let child = Device {
+        name: "Child".into(),
+        ratio: -0.25,
+        parent: Some(1),
+        nothing: (),
+        offset: -3,
+        tags: vec![],
+        pair: (false, 'c'),
+        events: vec![Event::Unit],
+        children: HashMap::new(),
+    };
+    Device {
+        name: "Root".into(),
+        ratio: 1.5,
+        parent: None,
+        nothing: (),
+        offset: 7,
+        tags: vec!["a".into(), "b".into()],
+        pair: (true, 'x'),
+        events: vec![
+            Event::Unit,
+            Event::Newtype(4),
+            Event::Tuple(1, "t".into()),
+            Event::Struct {
+                id: 2,
+                name: Some("s".into()),
+            },
+        ],
+        children: HashMap::from([("Bus".into(), vec![child])]),
+    }
+}
+
+#[test]
+fn test_struct_round_trip() {
+    let value = to_value(&device()).unwrap();
+    let OSValue::Dictionary(dict) = &value else {
+        panic!("Expected a dictionary, found {value}");
+    };
+    assert_eq!(dict["ratio"], OSValue::F64(1.5));
+    assert_eq!(dict["parent"], OSValue::Null);
+    assert_eq!(dict["nothing"], OSValue::Null);
+    assert_eq!(dict["tags"], OSValue::Vec(vec!["a".into(), "b".into()]));
+    assert_eq!(dict["pair"], OSValue::Vec(vec![true.into(), "x".into()]));
+    assert_eq!(from_value::<Device>(value).unwrap(), device());
+}
+
+#[test]
+fn test_enum_round_trip() {
+    for event in device().events {
+        assert_eq!(
+            from_value::<Event>(to_value(&event).unwrap()).unwrap(),
+            event
+        );
+    }
+    assert_eq!(to_value(&Event::Unit).unwrap(), "Unit".into());
+    assert_eq!(
+        to_value(&Event::Newtype(4)).unwrap(),
+        OSValue::Dictionary(HashMap::from([("Newtype".into(), 4u32.into())]))
+    );
+}
+
+#[test]
+fn test_value_round_trip() {
+    let values = [
+        OSValue::Bool(true),
+        OSValue::String("s".into()),
+        OSValue::USize(1),
+        OSValue::U64(u64::MAX),
+        OSValue::U32(2),
+        OSValue::U16(3),
+        OSValue::U8(4),
+        OSValue::ISize(-1),
+        OSValue::I64(i64::MIN),
+        OSValue::I32(-2),
+        OSValue::I16(-3),
+        OSValue::I8(-4),
+        OSValue::Vec(vec![OSValue::Null, OSValue::Vec(vec![1u8.into()])]),
+        OSValue::Dictionary(HashMap::from([(
+            "Nested".into(),
+            OSValue::Dictionary(HashMap::from([("Key".into(), 5u16.into())])),
+        )])),
+        (1u32, "b").into(),
+        OSValue::Data(vec![0xDE, 0xAD]),
+        OSValue::F64(-2.5),
+        OSValue::Null,
+    ];
+    for value in values {
+        assert_eq!(
+            from_value::<OSValue>(to_value(&value).unwrap()).unwrap(),
+            value
+        );
+    }
+}
+
+#[test]
+fn test_data() {
+    assert_eq!(
+        from_value::<Vec<u8>>(OSValue::Data(vec![1, 2, 3])).unwrap(),
+        [1, 2, 3]
+    );
+    assert_eq!(
+        from_value::<Vec<u8>>(OSValue::Vec(vec![1u8.into(), 2u8.into()])).unwrap(),
+        [1, 2]
+    );
+    assert_eq!(Vec::<u8>::try_from(OSValue::Data(vec![4])).unwrap(), [4]);
+}
+
+#[test]
+fn test_type_mismatch() {
+    assert_eq!(
+        u64::try_from(OSValue::Null),
+        Err(OSValueError::TypeMismatch {
+            expected: OSValueType::U64,
+            found: OSValueType::Null,
+        })
+    );
+    assert_eq!(
+        <&[u8]>::try_from(&OSValue::F64(1.0)),
+        Err(OSValueError::TypeMismatch {
+            expected: OSValueType::Data,
+            found: OSValueType::F64,
+        })
+    );
+    assert_eq!(
+        <(&u8, &str)>::try_from(&OSValue::Vec(vec![])),
+        Err(OSValueError::TypeMismatch {
+            expected: OSValueType::Tuple,
+            found: OSValueType::Vec,
+        })
+    );
+    assert_eq!(
+        from_value::<Event>(OSValue::U8(1)),
+        Err(OSValueError::TypeMismatch {
+            expected: OSValueType::Dictionary,
+            found: OSValueType::U8,
+        })
+    );
+    assert_eq!(
+        from_value::<Event>(OSValue::Dictionary(HashMap::from([(
+            "Tuple".into(),
+            OSValue::U8(1)
+        )]))),
+        Err(OSValueError::TypeMismatch {
+            expected: OSValueType::Vec,
+            found: OSValueType::U8,
+        })
+    );
+    assert_eq!(
+        to_value(&HashMap::<u8, u8>::from([(1, 2)])),
+        Err(OSValueError::TypeMismatch {
+            expected: OSValueType::String,
+            found: OSValueType::U8,
+        })
+    );
+    assert!(matches!(
+        from_value::<u8>("a".into()),
+        Err(OSValueError::Custom(_))
+    ));
+}
+
+#[test]
+fn test_non_finite() {
+    assert!(OSValue::F64(0.5).is_finite());
+    assert!(!OSValue::F64(f64::NAN).is_finite());
+    assert!(!OSValue::Vec(vec![OSValue::F64(f64::INFINITY)]).is_finite());
+    assert!(!OSValue::Dictionary(HashMap::from([(
+        "Key".into(),
+        (OSValue::Null, OSValue::F64(f64::NEG_INFINITY)).into()
+    )]))
+    .is_finite());
+
+    // NaN never equals itself, which is why the OSDT refuses it.
+    assert_ne!(OSValue::F64(f64::NAN), OSValue::F64(f64::NAN));
+    assert!(postcard::to_allocvec(&Canonical(&OSValue::F64(f64::NAN))).is_err());
+    assert!(
+        postcard::to_allocvec(&Canonical(&OSValue::Vec(vec![OSValue::F64(f64::INFINITY)])))
+            .is_err()
+    );
+    assert!(postcard::to_allocvec(&Canonical(&OSValue::F64(1.0))).is_ok());
+}
+
+#[test]
+fn test_canonical() {
+    let values = [
+        OSValue::Bool(false),
+        OSValue::String("s".into()),
+        OSValue::USize(1),
+        OSValue::U64(2),
+        OSValue::U32(3),
+        OSValue::U16(4),
+        OSValue::U8(5),
+        OSValue::ISize(-1),
+        OSValue::I64(-2),
+        OSValue::I32(-3),
+        OSValue::I16(-4),
+        OSValue::I8(-5),
+        OSValue::Vec(vec![OSValue::U8(1)]),
+        OSValue::Dictionary(HashMap::from([("Key".into(), OSValue::Null)])),
+        (1u8, 2u8).into(),
+        OSValue::Data(vec![1]),
+        OSValue::F64(1.0),
+        OSValue::Null,
+    ];
+    // The variant indices must match the derived ones for snapshots to deserialise.
+    for value in &values {
+        assert_eq!(
+            postcard::to_allocvec(&Canonical(value)).unwrap(),
+            postcard::to_allocvec(value).unwrap(),
+            "{value}"
+        );
+    }
+
+    let dict = OSValue::Dictionary((0..32).map(|i| (i.to_string(), i.into())).collect());
+    let data = postcard::to_allocvec(&Canonical(&dict)).unwrap();
+    assert_eq!(postcard::from_bytes::<OSValue>(&data).unwrap(), dict);
+    let OSValue::Dictionary(dict) = dict else {
+        unreachable!()
+    };
+    let mut expected = vec![OSValueType::Dictionary as u8];
+    expected.extend(postcard::to_allocvec(&dict.into_iter().collect::<BTreeMap<_, _>>()).unwrap());
+    assert_eq!(data, expected);
+}
@@ -0,0 +1,11 @@
+// Copyright (c) ChefKiss 2021-2025. Licensed under the Thou Shalt Not Profit License version 1.5. See LICENSE for details.
+
+//! SkyKit, along with `core` and `alloc`, as a single shared object.
+//!
+//! An extension links against it by depending on this crate and building with
+//! `-C prefer-dynamic`, the loader then maps it from the extension cache.
+
+#![no_std]
+#![deny(warnings, clippy::nursery, unused_extern_crates)]
+
+pub use skykit;
@@ -5,7 +5,7 @@
 
 use skybuffer::pixel::PixelFormat;
 
-pub const CURRENT_REVISION: u64 = 0x1D;
+pub const CURRENT_REVISION: u64 = 0x20;
 
 pub type EntryPoint = extern "sysv64" fn(&'static BootInfo) -> !;
 
@@ -75,6 +75,9 @@ pub struct BootInfo {
     pub revision: u64,
     pub verbose: bool,
     pub serial_enabled: bool,
+    pub osdt_snapshot: bool,
+    pub oom_kill: bool,
+    pub benchmark: bool,
     pub memory_map: &'static [MemoryEntry],
     pub frame_buffer: Option<&'static FrameBufferInfo>,
     pub acpi_rsdp: *const u8,
@@ -87,6 +90,9 @@ impl BootInfo {
     pub fn new(
         verbose: bool,
         serial_enabled: bool,
+        osdt_snapshot: bool,
+        oom_kill: bool,
+        benchmark: bool,
         frame_buffer: Option<&'static FrameBufferInfo>,
         acpi_rsdp: *const u8,
         fkcache: &'static [u8],
@@ -95,6 +101,9 @@ impl BootInfo {
             revision: CURRENT_REVISION,
             verbose,
             serial_enabled,
+            osdt_snapshot,
+            oom_kill,
+            benchmark,
             memory_map: Default::default(),
             frame_buffer,
             acpi_rsdp,
@@ -253,7 +253,7 @@ impl LocalAPIC {
         );
     }
 
-    pub fn setup_timer(&self, timer: &impl crate::timer::Timer) {
+    pub fn setup_timer(&self, timer: &impl crate::timer::Timer) -> u32 {
         self.set_timer_divide(0x3);
         self.set_timer_init_count(0xFFFF_FFFF);
 
@@ -270,6 +270,7 @@ impl LocalAPIC {
         );
         self.set_timer_divide(0x3);
         self.set_timer_init_count(ticks_per_ms);
+        ticks_per_ms
     }
 }
 
@@ -13,7 +13,9 @@ pub mod tables;
 
 pub struct ACPIState {
     pub version: u8,
-    pub tables: Vec<&'static tables::SystemDescTableHeader>,
+    /// Copies of the tables on the kernel heap along with their firmware address, so that the
+    /// ACPI reclaimable memory can be freed.
+    pub tables: Vec<(u64, &'static tables::SystemDescTableHeader)>,
 }
 
 impl ACPIState {
@@ -28,7 +30,18 @@ impl ACPIState {
             }
 
             trace!("Table: {ent:#X?}");
-            tables.push(ent);
+            let copy = unsafe {
+                core::slice::from_raw_parts(
+                    (ent as *const tables::SystemDescTableHeader).cast::<u8>(),
+                    ent.length(),
+                )
+            }
+            .to_vec()
+            .leak();
+            tables.push((
+                ent as *const _ as u64 - amd64::paging::PHYS_VIRT_OFFSET,
+                unsafe { &*copy.as_ptr().cast() },
+            ));
         }
 
         Self {
@@ -40,8 +53,8 @@ impl ACPIState {
     pub fn find<T>(&self, signature: &str) -> Option<&'static T> {
         self.tables
             .iter()
-            .find(|&a| a.signature() == signature)
-            .map(|&v| unsafe { &*(v as *const tables::SystemDescTableHeader).cast::<T>() })
+            .find(|(_, a)| a.signature() == signature)
+            .map(|&(_, v)| unsafe { &*(v as *const tables::SystemDescTableHeader).cast::<T>() })
     }
 }
 
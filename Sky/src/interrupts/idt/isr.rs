@@ -55,6 +55,7 @@ unsafe extern "sysv64" fn isr_jmp_common() {
 }
 
 unsafe extern "sysv64" fn isr_handler(regs: &mut crate::system::RegisterState) {
+    crate::system::hardening::close_user_access();
     let handler = &(*super::HANDLERS.get())[regs.int_num as usize];
     (handler.func)(regs);
     if handler.is_irq {
@@ -10,7 +10,7 @@ use alloc::boxed::Box;
 use hashbrown::HashMap;
 use incr_id::IncrementalIDGen;
 use skykit::{osdtentry::OSDTENTRY_NAME_KEY, SKExtensions};
-use system::{pmm::BitmapAllocator, state::OSDTEntry};
+use system::state::OSDTEntry;
 
 #[macro_use]
 extern crate alloc;
@@ -22,7 +22,6 @@ extern crate itertools;
 extern crate bitfield_struct;
 
 mod acpi;
-mod bitmap;
 mod incr_id;
 mod interrupts;
 mod logger;
@@ -55,21 +54,26 @@ macro_rules! cli {
 pub fn init_core(boot_info: &skyliftkit::BootInfo) {
     let state = unsafe { &mut *crate::system::state::SYS_STATE.get() };
     state.verbose = boot_info.verbose;
-    state.serial_enabled = boot_info.serial_enabled || boot_info.frame_buffer.is_none();
+    state.serial_enabled =
+        boot_info.serial_enabled || boot_info.osdt_snapshot || boot_info.frame_buffer.is_none();
+    state.snapshot_on_idle = boot_info.osdt_snapshot;
+    state.oom_kill = boot_info.oom_kill;
 
     unsafe {
         crate::system::gdt::GDTR.load();
         crate::interrupts::idt::IDTR.load();
         crate::interrupts::init_quirks();
         crate::system::exceptions::init();
+        crate::system::hardening::init();
     }
 
-    state.pmm = Some(BitmapAllocator::new(boot_info.memory_map).into());
+    state.pmm = Some(crate::system::pmm::new(boot_info.memory_map).into());
 
     let root = OSDTEntry {
         properties: HashMap::from([
             (OSDTENTRY_NAME_KEY.into(), "Root".into()),
             ("Version".into(), "0.0.1".into()),
+            ("Benchmark".into(), boot_info.benchmark.into()),
         ]),
         ..Default::default()
     };
@@ -127,8 +131,11 @@ extern "C" fn kernel_main(boot_info: &'static skyliftkit::BootInfo) -> ! {
 
     let fkcache: SKExtensions = postcard::from_bytes(boot_info.fkcache).unwrap();
     state.fkcache = Some(fkcache.into());
+    // Everything needed from the boot info was copied by now.
+    system::pmm::reclaim(boot_info.memory_map);
     state.scheduler =
         Some(system::tasking::scheduler::Scheduler::new(&acpi::get_hpet(state)).into());
+    system::osdt::publish(state);
 
     system::fkext::spawn_initial_matches();
 
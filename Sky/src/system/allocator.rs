@@ -3,38 +3,124 @@
 use amd64::paging::PAGE_SIZE;
 
 #[global_allocator]
-static GLOBAL_ALLOCATOR: KernAllocator = KernAllocator;
+static GLOBAL_ALLOCATOR: KernAllocator = KernAllocator(spin::Mutex::new(Heap::new()));
 
-struct KernAllocator;
+/// Object sizes of the slabs, larger allocations take whole pages.
+pub const SIZE_CLASSES: [usize; 8] = [16, 32, 64, 128, 256, 512, 1024, 2048];
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct HeapStats {
+    /// Live objects per size class.
+    pub objects: [u64; SIZE_CLASSES.len()],
+    /// Pages carved into objects, kept around once freed.
+    pub slab_pages: u64,
+    /// Pages of live page-granular allocations.
+    pub large_pages: u64,
+}
+
+struct Heap {
+    /// Free objects per size class, each holding the address of the next one.
+    free_lists: [usize; SIZE_CLASSES.len()],
+    stats: HeapStats,
+}
+
+impl Heap {
+    const fn new() -> Self {
+        Self {
+            free_lists: [0; SIZE_CLASSES.len()],
+            stats: HeapStats {
+                objects: [0; SIZE_CLASSES.len()],
+                slab_pages: 0,
+                large_pages: 0,
+            },
+        }
+    }
+
+    fn size_class(layout: core::alloc::Layout) -> Option<usize> {
+        let size = layout.size().max(layout.align());
+        SIZE_CLASSES.iter().position(|&v| v >= size)
+    }
+
+    fn alloc_pages(count: u64) -> *mut u8 {
+        super::pmm::alloc(count).map_or(core::ptr::null_mut(), |v| {
+            (v + amd64::paging::PHYS_VIRT_OFFSET) as *mut u8
+        })
+    }
+
+    unsafe fn alloc_small(&mut self, class: usize) -> *mut u8 {
+        if self.free_lists[class] == 0 {
+            let slab = Self::alloc_pages(1);
+            if slab.is_null() {
+                return slab;
+            }
+            self.stats.slab_pages += 1;
+            let size = SIZE_CLASSES[class];
+            for offset in (0..PAGE_SIZE as usize).step_by(size).rev() {
+                let obj = slab.add(offset);
+                obj.cast::<usize>().write(self.free_lists[class]);
+                self.free_lists[class] = obj as usize;
+            }
+        }
+
+        let obj = self.free_lists[class] as *mut u8;
+        self.free_lists[class] = obj.cast::<usize>().read();
+        self.stats.objects[class] += 1;
+        obj
+    }
+
+    unsafe fn free_small(&mut self, ptr: *mut u8, class: usize) {
+        ptr.cast::<usize>().write(self.free_lists[class]);
+        self.free_lists[class] = ptr as usize;
+        self.stats.objects[class] -= 1;
+    }
+}
+
+struct KernAllocator(spin::Mutex<Heap>);
 
 unsafe impl core::alloc::GlobalAlloc for KernAllocator {
     unsafe fn alloc(&self, layout: core::alloc::Layout) -> *mut u8 {
-        let count = layout.pad_to_align().size().div_ceil(PAGE_SIZE as _) as u64;
-        let pmm = (*super::state::SYS_STATE.get()).pmm.as_ref().unwrap();
+        let mut heap = self.0.lock();
+        if let Some(class) = Heap::size_class(layout) {
+            return heap.alloc_small(class);
+        }
 
-        let ptr = pmm.lock().alloc(count);
-        if ptr.is_null() {
-            ptr
-        } else {
-            let ptr = ptr.map_addr(|v| v.saturating_add(amd64::paging::PHYS_VIRT_OFFSET as _));
-            ptr.write_bytes(0, (count * PAGE_SIZE) as _);
-            ptr
+        let count = layout.pad_to_align().size().div_ceil(PAGE_SIZE as _) as u64;
+        let ptr = Heap::alloc_pages(count);
+        if !ptr.is_null() {
+            heap.stats.large_pages += count;
         }
+        ptr
     }
 
     unsafe fn alloc_zeroed(&self, layout: core::alloc::Layout) -> *mut u8 {
-        self.alloc(layout) // Memory is already zeroed by default
+        let ptr = self.alloc(layout);
+        if !ptr.is_null() {
+            ptr.write_bytes(0, layout.size());
+        }
+        ptr
     }
 
     unsafe fn dealloc(&self, ptr: *mut u8, layout: core::alloc::Layout) {
+        let mut heap = self.0.lock();
+        if let Some(class) = Heap::size_class(layout) {
+            heap.free_small(ptr, class);
+            return;
+        }
+
+        let count = layout.pad_to_align().size().div_ceil(PAGE_SIZE as _) as u64;
         let pmm = (*super::state::SYS_STATE.get()).pmm.as_ref().unwrap();
         pmm.lock().free(
             ptr.map_addr(|v| v - amd64::paging::PHYS_VIRT_OFFSET as usize),
-            layout.pad_to_align().size().div_ceil(PAGE_SIZE as _) as _,
+            count,
         );
+        heap.stats.large_pages -= count;
     }
 }
 
+pub fn stats() -> HeapStats {
+    GLOBAL_ALLOCATOR.0.lock().stats
+}
+
 #[alloc_error_handler]
 pub fn alloc_error(layout: core::alloc::Layout) -> ! {
     panic!("Failed to allocate memory: {layout:#X?}");
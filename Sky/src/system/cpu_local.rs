@@ -0,0 +1,29 @@
+// Copyright (c) ChefKiss 2021-2025. Licensed under the Thou Shalt Not Profit License version 1.5. See LICENSE for details.
+
+use core::cell::SyncUnsafeCell;
+
+use amd64::msr::{
+    gs_base::{GSBase, KernelGSBase},
+    ModelSpecificReg,
+};
+
+// Reached through `gs` after `swapgs`, so the layout is shared with assembly.
+#[derive(Debug)]
+#[repr(C)]
+pub struct CPULocal {
+    pub kernel_rsp: u64,
+    pub user_rsp: u64,
+}
+
+pub const KERNEL_RSP_OFF: usize = core::mem::offset_of!(CPULocal, kernel_rsp);
+pub const USER_RSP_OFF: usize = core::mem::offset_of!(CPULocal, user_rsp);
+
+pub static BSP: SyncUnsafeCell<CPULocal> = SyncUnsafeCell::new(CPULocal {
+    kernel_rsp: 0,
+    user_rsp: 0,
+});
+
+pub unsafe fn install() {
+    GSBase::new().write();
+    KernelGSBase::new().with_base(BSP.get() as u64).write();
+}
@@ -1,9 +1,55 @@
 // Copyright (c) ChefKiss 2021-2025. Licensed under the Thou Shalt Not Profit License version 1.5. See LICENSE for details.
 
+use crate::system::{pmm::OutOfMemory, tasking::STACK_GUARD_LEN};
+
 pub unsafe extern "sysv64" fn page_fault(regs: &mut crate::system::RegisterState) {
     let mut cr2: u64;
     core::arch::asm!("mov {}, cr2", out(reg) cr2, options(nomem, nostack, preserves_flags));
 
+    if (regs.err_code & (1 << 2)) != 0 {
+        let sys_state = &*crate::system::state::SYS_STATE.get();
+        let mut scheduler = sys_state.scheduler.as_ref().unwrap().lock();
+        let process = scheduler.current_process();
+        let handled = if (regs.err_code & (1 << 0)) == 0 {
+            // Non-present access, possibly to an on-demand area that was not touched yet.
+            process.map_or(Ok(false), |v| v.fault_in(cr2))
+        } else if (regs.err_code & 0b11) == 0b11 {
+            // Write to a page shared with another area.
+            process.map_or(Ok(false), |v| v.copy_on_write(cr2))
+        } else {
+            Ok(false)
+        };
+        match handled {
+            Ok(true) => return,
+            Ok(false) => {}
+            Err(OutOfMemory) => {
+                // The access is retried once another process made room.
+                let pid = scheduler.current_pid;
+                match scheduler.out_of_memory() {
+                    Some(v) if Some(v) == pid => scheduler.schedule(regs),
+                    Some(_) => {}
+                    None => {
+                        drop(scheduler);
+                        let msg = format!("Out of memory while backing the page at {cr2:#X}.");
+                        super::handle_exception("page fault", &msg, regs);
+                    }
+                }
+                return;
+            }
+        }
+        let overflow = scheduler
+            .current_tid
+            .and_then(|v| scheduler.threads.get(&v))
+            .filter(|v| (v.stack_addr - STACK_GUARD_LEN..v.stack_addr).contains(&cr2))
+            .map(|v| (v.pid, v.id));
+        drop(scheduler);
+        if let Some((pid, tid)) = overflow {
+            let msg = format!("Stack overflow in PID {pid} (TID {tid}) at {cr2:#X}.");
+            super::handle_exception("page fault", &msg, regs);
+            return;
+        }
+    }
+
     let msg = format!(
         "There was a {} while {} a {} page at {cr2:#X}.{}{}{}{}",
         if (regs.err_code & (1 << 0)) == 0 {
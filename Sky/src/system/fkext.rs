@@ -1,72 +1,96 @@
 // Copyright (c) ChefKiss 2021-2025. Licensed under the Thou Shalt Not Profit License version 1.5. See LICENSE for details.
 
-use alloc::vec::Vec;
-use core::hash::Hash;
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
 
 use hashbrown::HashMap;
 use skykit::{
-    osdtentry::{OSDTENTRY_NAME_KEY, SKEXT_MATCH_KEY, SKEXT_PROC_KEY},
+    osdtentry::{
+        OSDTENTRY_NAME_KEY, SKEXT_ERROR_KEY, SKEXT_MATCH_KEY, SKEXT_OPTION_KEYS, SKEXT_PROC_KEY,
+    },
+    osvalue::OSValue,
     SKExtension,
 };
 
 use super::tasking::scheduler::Scheduler;
-use crate::incr_id::IncrementalIDGen;
-
-fn is_subset<K: Eq + Hash, V: Eq>(a: &HashMap<K, V>, b: &HashMap<K, V>) -> bool {
-    if a.len() > b.len() {
-        return false;
-    }
 
-    a.iter().all(|(k, v)| b.get(k) == Some(v))
+/// Whether `properties` has every matching key of `personality`.
+fn is_match(personality: &HashMap<String, OSValue>, properties: &HashMap<String, OSValue>) -> bool {
+    personality
+        .iter()
+        .filter(|(k, _)| !SKEXT_OPTION_KEYS.contains(&k.as_str()))
+        .all(|(k, v)| properties.get(k) == Some(v))
 }
 
 fn load_fkext(
-    ent: &mut super::state::OSDTEntry,
+    parent: u64,
     info: &SKExtension,
     personality: &str,
     payload: &[u8],
-    dt_id_gen: &mut IncrementalIDGen,
+    libraries: &HashMap<String, Vec<u8>>,
     scheduler: &mut Scheduler,
-) -> (u64, spin::Mutex<super::state::OSDTEntry>) {
+) {
     debug!(
-        "SkyKit extension {} matched <{}> personality {personality}",
-        info.identifier, ent.id
+        "SkyKit extension {} matched <{parent}> personality {personality}",
+        info.identifier
+    );
+    let mut properties = HashMap::from([
+        (
+            OSDTENTRY_NAME_KEY.into(),
+            info.identifier.rsplit('.').next().unwrap().into(),
+        ),
+        (
+            SKEXT_MATCH_KEY.into(),
+            (info.identifier.as_str(), personality).into(),
+        ),
+    ]);
+    // Failed matches are still inserted so that they are not retried on every change.
+    // Malformed limits are rejected when building the cache.
+    let memory_limit = info.memory_limit(personality).ok().flatten();
+    let thread =
+        match scheduler.spawn_proc(info.identifier.clone(), payload, libraries, memory_limit) {
+            Ok(v) => v,
+            Err(e) => {
+                error!("Failed to spawn SkyKit extension {}: {e}", info.identifier);
+                properties.insert(SKEXT_ERROR_KEY.into(), e.to_string().as_str().into());
+                super::osdt::insert_entry(
+                    parent,
+                    super::state::OSDTEntry {
+                        properties,
+                        ..Default::default()
+                    },
+                );
+                return;
+            }
+        };
+    properties.insert(SKEXT_PROC_KEY.into(), thread.pid.into());
+    thread.regs.rdi = super::osdt::insert_entry(
+        parent,
+        super::state::OSDTEntry {
+            properties,
+            attached_pid: Some(thread.pid),
+            ..Default::default()
+        },
     );
-    let thread = scheduler.spawn_proc(info.identifier.clone(), payload);
-    let new = super::state::OSDTEntry {
-        id: dt_id_gen.next(),
-        parent: Some(ent.id.into()),
-        properties: HashMap::from([
-            (
-                OSDTENTRY_NAME_KEY.into(),
-                info.identifier.rsplit('.').next().unwrap().into(),
-            ),
-            (
-                SKEXT_MATCH_KEY.into(),
-                (info.identifier.as_str(), personality).into(),
-            ),
-            (SKEXT_PROC_KEY.into(), thread.pid.into()),
-        ]),
-        ..Default::default()
-    };
-    ent.children.push(new.id.into());
-    thread.regs.rdi = new.id;
-    (new.id, new.into())
 }
 
 pub fn handle_change(scheduler: &mut Scheduler, ent: skykit::osdtentry::OSDTEntry) {
     let state = unsafe { &*super::state::SYS_STATE.get() };
 
     let dt_index = state.dt_index.as_ref().unwrap();
-    let mut dt_id_gen = state.dt_id_gen.as_ref().unwrap().lock();
+    let fkcache = state.fkcache.as_ref().unwrap().lock();
+    let id: u64 = ent.into();
 
-    let new: Vec<_> = {
+    let matches: Vec<_> = {
         let dt_index = dt_index.read();
-        let mut ent = dt_index.get::<u64>(&ent.into()).unwrap().lock();
-        let fkcache = &state.fkcache.as_ref().unwrap().lock().0;
+        let ent = dt_index.get(&id).unwrap().lock();
         fkcache
+            .extensions
             .iter()
-            .filter_map(|(info, payload)| {
+            .enumerate()
+            .filter_map(|(i, (info, _))| {
                 for (personality, matching) in &info.personalities {
                     let match_ = (info.identifier.as_str(), personality.as_str()).into();
                     let attached = ent
@@ -74,15 +98,8 @@ pub fn handle_change(scheduler: &mut Scheduler, ent: skykit::osdtentry::OSDTEntr
                         .iter()
                         .filter_map(|id| dt_index.get::<u64>(&id.into()))
                         .any(|v| v.lock().properties.get(SKEXT_MATCH_KEY) == Some(&match_));
-                    if !attached && is_subset(matching, &ent.properties) {
-                        return Some(load_fkext(
-                            &mut ent,
-                            info,
-                            personality,
-                            payload,
-                            &mut dt_id_gen,
-                            scheduler,
-                        ));
+                    if !attached && is_match(matching, &ent.properties) {
+                        return Some((i, personality.as_str()));
                     }
                 }
                 None
@@ -90,36 +107,63 @@ pub fn handle_change(scheduler: &mut Scheduler, ent: skykit::osdtentry::OSDTEntr
             .collect()
     };
 
-    dt_index.write().extend(new);
+    for (i, personality) in matches {
+        let (info, payload) = &fkcache.extensions[i];
+        load_fkext(
+            id,
+            info,
+            personality,
+            payload,
+            &fkcache.libraries,
+            scheduler,
+        );
+    }
 }
 
 pub fn spawn_initial_matches() {
     let state = unsafe { &*super::state::SYS_STATE.get() };
 
     let dt_index = state.dt_index.as_ref().unwrap();
-    let mut dt_id_gen = state.dt_id_gen.as_ref().unwrap().lock();
     let mut scheduler = state.scheduler.as_ref().unwrap().lock();
+    let fkcache = state.fkcache.as_ref().unwrap().lock();
 
-    let mut newly_matched = vec![];
-    for ((info, payload), mut ent) in iproduct!(
-        &state.fkcache.as_ref().unwrap().lock().0,
+    let matches: Vec<_> = iproduct!(
+        fkcache.extensions.iter().enumerate(),
         dt_index.read().values()
     )
-    .map(|(info, ent)| (info, ent.lock()))
-    {
-        for (personality, matching) in &info.personalities {
-            if is_subset(matching, &ent.properties) {
-                let new = load_fkext(
-                    &mut ent,
-                    info,
-                    personality,
-                    payload,
-                    &mut dt_id_gen,
-                    &mut scheduler,
-                );
-                newly_matched.push(new);
-            }
+    .flat_map(|((i, (info, _)), ent)| {
+        let ent = ent.lock();
+        info.personalities
+            .iter()
+            .filter(|(_, matching)| is_match(matching, &ent.properties))
+            .map(|(personality, _)| (ent.id, i, personality.as_str()))
+            .collect::<Vec<_>>()
+    })
+    .collect();
+
+    for (id, i, personality) in matches {
+        let (info, payload) = &fkcache.extensions[i];
+        load_fkext(
+            id,
+            info,
+            personality,
+            payload,
+            &fkcache.libraries,
+            &mut scheduler,
+        );
+    }
+}
+
+pub fn detach_process(pid: u64) {
+    let state = unsafe { &*super::state::SYS_STATE.get() };
+
+    for ent in state.dt_index.as_ref().unwrap().read().values() {
+        let mut ent = ent.lock();
+        if ent.owner_pid == Some(pid) {
+            ent.owner_pid = None;
+        }
+        if ent.attached_pid == Some(pid) {
+            ent.attached_pid = None;
         }
     }
-    dt_index.write().extend(newly_matched);
 }
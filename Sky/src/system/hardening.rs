@@ -0,0 +1,48 @@
+// Copyright (c) ChefKiss 2021-2025. Licensed under the Thou Shalt Not Profit License version 1.5. See LICENSE for details.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use amd64::{
+    control::ControlReg4,
+    cpuid::CPUIdentification,
+    msr::{efer::ExtendedFeatureEnableReg, ModelSpecificReg},
+};
+
+/// Set once NX is enabled; until then no-execute mappings must not be created.
+pub static NX_ACTIVE: AtomicBool = AtomicBool::new(false);
+/// Set once SMAP is enabled, after which user memory is only touched between `stac` and `clac`.
+pub static SMAP_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+pub unsafe fn init() {
+    let cpuid = CPUIdentification::new();
+    let (ext, structured) = (cpuid.ext_features, cpuid.structured_ext_features);
+
+    if ext.nx() {
+        ExtendedFeatureEnableReg::read()
+            .with_no_execute(true)
+            .write();
+        NX_ACTIVE.store(true, Ordering::Relaxed);
+    }
+    ControlReg4::read()
+        .with_smep(structured.smep())
+        .with_smap(structured.smap())
+        .with_umip(structured.umip())
+        .write();
+    SMAP_ACTIVE.store(structured.smap(), Ordering::Relaxed);
+
+    debug!(
+        "NX: {}, SMEP: {}, SMAP: {}, UMIP: {}",
+        ext.nx(),
+        structured.smep(),
+        structured.smap(),
+        structured.umip()
+    );
+}
+
+/// Clears `RFLAGS.AC`, which user mode may leave set when entering through an interrupt gate.
+#[inline]
+pub fn close_user_access() {
+    if SMAP_ACTIVE.load(Ordering::Relaxed) {
+        unsafe { core::arch::asm!("clac", options(nostack)) }
+    }
+}
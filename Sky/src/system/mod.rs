@@ -1,9 +1,12 @@
 // Copyright (c) ChefKiss 2021-2025. Licensed under the Thou Shalt Not Profit License version 1.5. See LICENSE for details.
 
 pub mod allocator;
+pub mod cpu_local;
 pub mod exceptions;
 pub mod fkext;
 pub mod gdt;
+pub mod hardening;
+pub mod osdt;
 mod panic;
 pub mod pmm;
 pub mod serial;
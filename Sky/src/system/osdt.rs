@@ -0,0 +1,328 @@
+// Copyright (c) ChefKiss 2021-2025. Licensed under the Thou Shalt Not Profit License version 1.5. See LICENSE for details.
+
+use alloc::{string::String, vec::Vec};
+use core::{fmt::Write, sync::atomic::Ordering};
+
+use amd64::{cpuid::CPUIdentification, paging::PAGE_SIZE};
+use hashbrown::HashMap;
+use skybuddy::Zone;
+use skykit::{
+    osdtentry::OSDTENTRY_NAME_KEY,
+    osvalue::OSValue,
+    snapshot::{OSDTSnapshot, SNAPSHOT_BEGIN_MARKER, SNAPSHOT_END_MARKER},
+};
+
+use super::{
+    state::{OSDTEntry, SystemState},
+    tasking::scheduler::Scheduler,
+};
+use crate::acpi::tables::hpet::Hpet;
+
+pub const ROOT_ID: u64 = 0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiveSource {
+    Memory,
+    Process(u64),
+    Thread(u64),
+}
+
+pub fn insert_entry(parent: u64, mut new: OSDTEntry) -> u64 {
+    let state = unsafe { &*super::state::SYS_STATE.get() };
+    let dt_index = state.dt_index.as_ref().unwrap();
+
+    new.id = state.dt_id_gen.as_ref().unwrap().lock().next();
+    new.parent = Some(parent.into());
+    let id = new.id;
+
+    let mut dt_index = dt_index.write();
+    dt_index
+        .get(&parent)
+        .unwrap()
+        .lock()
+        .children
+        .push(id.into());
+    dt_index.insert(id, new.into());
+    id
+}
+
+pub fn new_entry(
+    parent: u64,
+    name: &str,
+    mut properties: HashMap<String, OSValue>,
+    live: Option<LiveSource>,
+) -> u64 {
+    properties.insert(OSDTENTRY_NAME_KEY.into(), name.into());
+    insert_entry(
+        parent,
+        OSDTEntry {
+            properties,
+            live,
+            ..Default::default()
+        },
+    )
+}
+
+pub fn remove_entry(id: u64) {
+    let state = unsafe { &*super::state::SYS_STATE.get() };
+    let mut dt_index = state.dt_index.as_ref().unwrap().write();
+    let mut dt_id_gen = state.dt_id_gen.as_ref().unwrap().lock();
+
+    let Some(ent) = dt_index.remove(&id) else {
+        return;
+    };
+    let ent = ent.into_inner();
+    if let Some(parent) = ent.parent.and_then(|v| dt_index.get(&u64::from(v))) {
+        parent.lock().children.retain(|v| u64::from(v) != id);
+    }
+    dt_id_gen.free(id);
+
+    let mut pending: Vec<u64> = ent.children.iter().map(Into::into).collect();
+    while let Some(id) = pending.pop() {
+        let Some(ent) = dt_index.remove(&id) else {
+            continue;
+        };
+        pending.extend(ent.into_inner().children.iter().map(u64::from));
+        dt_id_gen.free(id);
+    }
+}
+
+pub fn refresh(ent: &mut OSDTEntry, scheduler: &Scheduler) {
+    let Some(live) = ent.live else {
+        return;
+    };
+
+    match live {
+        LiveSource::Memory => {
+            let state = unsafe { &*super::state::SYS_STATE.get() };
+            // Allocating takes the PMM lock, so the counters are copied out before inserting.
+            let (total_pages, free_pages, zones) = {
+                let pmm = state.pmm.as_ref().unwrap().lock();
+                (
+                    pmm.total_pages,
+                    pmm.free_pages,
+                    Zone::ALL.map(|zone| (zone, pmm.zone_stats(zone))),
+                )
+            };
+            ent.properties
+                .insert("TotalPages".into(), total_pages.into());
+            ent.properties.insert("FreePages".into(), free_pages.into());
+            ent.properties
+                .insert("UsedPages".into(), (total_pages - free_pages).into());
+            for (zone, stats) in zones {
+                ent.properties
+                    .insert(format!("TotalPages{zone:?}"), stats.total_pages.into());
+                ent.properties
+                    .insert(format!("FreePages{zone:?}"), stats.free_pages.into());
+            }
+            ent.properties.insert(
+                "AllocationFailures".into(),
+                state.alloc_failures.load(Ordering::Relaxed).into(),
+            );
+            ent.properties.insert(
+                "OOMKills".into(),
+                state.oom_kills.load(Ordering::Relaxed).into(),
+            );
+
+            let heap = super::allocator::stats();
+            ent.properties
+                .insert("HeapSlabPages".into(), heap.slab_pages.into());
+            ent.properties
+                .insert("HeapLargePages".into(), heap.large_pages.into());
+            for (size, objects) in super::allocator::SIZE_CLASSES.iter().zip(heap.objects) {
+                ent.properties
+                    .insert(format!("HeapObjects{size}"), objects.into());
+            }
+        }
+        LiveSource::Process(pid) => {
+            let Some(process) = scheduler.processes.get(&pid) else {
+                return;
+            };
+            let usage = process.memory_usage();
+            ent.properties
+                .insert("PrivatePages".into(), usage.private.into());
+            ent.properties
+                .insert("SharedPages".into(), usage.shared.into());
+            ent.properties
+                .insert("MessagePages".into(), usage.message.into());
+            ent.properties
+                .insert("PageTablePages".into(), usage.page_tables.into());
+            ent.properties
+                .insert("CommittedPages".into(), usage.committed.into());
+        }
+        LiveSource::Thread(tid) => {
+            let Some(thread) = scheduler.threads.get(&tid) else {
+                return;
+            };
+            ent.properties
+                .insert("State".into(), format!("{:?}", thread.state).into());
+            ent.properties.insert(
+                "CPUTime".into(),
+                (thread.cpu_time / scheduler.tsc_ticks_per_ms.max(1)).into(),
+            );
+        }
+    }
+}
+
+fn snapshot_of(
+    dt_index: &HashMap<u64, spin::Mutex<OSDTEntry>>,
+    id: u64,
+    scheduler: &Scheduler,
+) -> OSDTSnapshot {
+    let (properties, children) = {
+        let mut ent = dt_index.get(&id).unwrap().lock();
+        refresh(&mut ent, scheduler);
+        (
+            ent.properties
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+            ent.children.iter().map(u64::from).collect::<Vec<_>>(),
+        )
+    };
+
+    OSDTSnapshot {
+        id,
+        properties,
+        children: children
+            .into_iter()
+            .map(|id| snapshot_of(dt_index, id, scheduler))
+            .collect(),
+    }
+}
+
+pub fn dump_snapshot(scheduler: &Scheduler) {
+    let state = unsafe { &*super::state::SYS_STATE.get() };
+    let snapshot = snapshot_of(&state.dt_index.as_ref().unwrap().read(), ROOT_ID, scheduler);
+    let data = serde_json::to_string(&snapshot).unwrap();
+
+    let mut serial = super::serial::SERIAL.lock();
+    writeln!(serial, "{SNAPSHOT_BEGIN_MARKER}").unwrap();
+    writeln!(serial, "{data}").unwrap();
+    writeln!(serial, "{SNAPSHOT_END_MARKER}").unwrap();
+}
+
+fn publish_cpus(state: &SystemState) {
+    let cpus = new_entry(ROOT_ID, "CPUs", HashMap::new(), None);
+    let cpuid = CPUIdentification::new();
+    let madt = state.madt.as_ref().unwrap().lock();
+
+    for lapic in &madt.proc_lapics {
+        let flags = lapic.flags;
+        let mut properties = HashMap::from([
+            ("ProcessorID".into(), lapic.acpi_uid.into()),
+            ("APICID".into(), lapic.apic_id.into()),
+            ("Enabled".into(), flags.enabled().into()),
+            ("OnlineCapable".into(), flags.online_capable().into()),
+        ]);
+        let is_bsp = lapic.apic_id == cpuid.misc.apic_id();
+        properties.insert("BootProcessor".into(), is_bsp.into());
+        if is_bsp {
+            properties.insert("Vendor".into(), cpuid.vendor_string.as_str().into());
+            properties.insert(
+                "Features".into(),
+                OSValue::Vec(
+                    cpuid
+                        .features
+                        .as_named()
+                        .into_iter()
+                        .chain(cpuid.structured_ext_features.as_named())
+                        .chain(cpuid.ext_features.as_named())
+                        .filter(|(_, v)| *v)
+                        .map(|(k, _)| k.into())
+                        .collect(),
+                ),
+            );
+        }
+        new_entry(cpus, "CPU", properties, None);
+    }
+}
+
+fn publish_memory(state: &SystemState) {
+    let highest_addr = state.pmm.as_ref().unwrap().lock().highest_addr;
+    new_entry(
+        ROOT_ID,
+        "Memory",
+        HashMap::from([
+            ("PageSize".into(), PAGE_SIZE.into()),
+            ("HighestAddress".into(), highest_addr.into()),
+            ("OOMKill".into(), state.oom_kill.into()),
+        ]),
+        Some(LiveSource::Memory),
+    );
+}
+
+fn publish_acpi(state: &SystemState) {
+    let acpi = state.acpi.as_ref().unwrap();
+    let root = new_entry(
+        ROOT_ID,
+        "ACPI",
+        HashMap::from([("Revision".into(), acpi.version.into())]),
+        None,
+    );
+
+    for &(addr, table) in &acpi.tables {
+        let (oem_revision, creator_revision) = (table.oem_revision, table.creator_revision);
+        new_entry(
+            root,
+            table.signature(),
+            HashMap::from([
+                ("Address".into(), addr.into()),
+                ("Length".into(), table.length().into()),
+                ("Revision".into(), table.revision.into()),
+                ("OEMID".into(), table.oem_id().into()),
+                ("OEMTableID".into(), table.oem_table_id().into()),
+                ("OEMRevision".into(), oem_revision.into()),
+                ("CreatorID".into(), table.creator_id().into()),
+                ("CreatorRevision".into(), creator_revision.into()),
+            ]),
+            None,
+        );
+    }
+}
+
+fn publish_timers(state: &SystemState) {
+    let timers = new_entry(ROOT_ID, "Timers", HashMap::new(), None);
+
+    if let Some(hpet) = state.acpi.as_ref().unwrap().find::<Hpet>("HPET") {
+        let caps = hpet.capabilities();
+        let address = hpet.address.address;
+        new_entry(
+            timers,
+            "HPET",
+            HashMap::from([
+                ("Address".into(), address.into()),
+                ("ClockPeriod".into(), caps.clk_period().into()),
+                ("TimerCount".into(), (caps.num_timers() + 1).into()),
+                ("Is64Bit".into(), caps.main_cnt_64bit().into()),
+                ("VendorID".into(), caps.vendor_id().into()),
+            ]),
+            None,
+        );
+    }
+
+    new_entry(
+        timers,
+        "PIT",
+        HashMap::from([("Frequency".into(), 1_193_182u32.into())]),
+        None,
+    );
+
+    let scheduler = state.scheduler.as_ref().unwrap().lock();
+    new_entry(
+        timers,
+        "LAPIC",
+        HashMap::from([
+            ("TicksPerMS".into(), scheduler.lapic_ticks_per_ms.into()),
+            ("TSCTicksPerMS".into(), scheduler.tsc_ticks_per_ms.into()),
+        ]),
+        None,
+    );
+}
+
+pub fn publish(state: &SystemState) {
+    publish_cpus(state);
+    publish_memory(state);
+    publish_acpi(state);
+    publish_timers(state);
+}
@@ -1,158 +1,110 @@
 // Copyright (c) ChefKiss 2021-2025. Licensed under the Thou Shalt Not Profit License version 1.5. See LICENSE for details.
 
-use amd64::paging::PAGE_SIZE;
-use skyliftkit::{MemoryData, MemoryEntry};
+use alloc::vec::Vec;
+use core::sync::atomic::Ordering;
 
-pub struct BitmapAllocator {
-    bitmap: &'static mut [u64],
-    highest_addr: u64,
-    pub free_pages: u64,
-    last_index: u64,
+use amd64::paging::PAGE_SIZE;
+use skybuddy::{BuddyAllocator, Zone};
+use skyliftkit::MemoryEntry;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutOfMemory;
+
+/// Allocates `count` contiguous pages, counting the failure if there is no room.
+pub fn alloc(count: u64) -> Result<u64, OutOfMemory> {
+    let state = unsafe { &*super::state::SYS_STATE.get() };
+    let phys = unsafe { state.pmm.as_ref().unwrap().lock().alloc(count) }.addr() as u64;
+    if phys == 0 {
+        state.alloc_failures.fetch_add(1, Ordering::Relaxed);
+        return Err(OutOfMemory);
+    }
+    Ok(phys)
 }
 
-impl BitmapAllocator {
-    #[inline]
-    pub fn new(mmap: &'static [MemoryEntry]) -> Self {
-        let highest_addr = mmap
-            .iter()
-            .flat_map(|v| {
-                let MemoryEntry::Usable(v) = v else {
-                    return None;
-                };
-                Some(v.base + v.length)
-            })
-            .max()
-            .unwrap();
-
-        let bitmap_sz = (highest_addr / PAGE_SIZE).div_ceil(8);
-        trace!(
-            "Highest usable address: {highest_addr:#X}, Bitmap size: {bitmap_sz} bytes, {} \
-             entries",
-            bitmap_sz / 8
+/// Allocates `count` zeroed pages, see [`alloc`].
+pub fn alloc_zeroed(count: u64) -> Result<u64, OutOfMemory> {
+    let phys = alloc(count)?;
+    unsafe {
+        core::ptr::write_bytes(
+            (phys + amd64::paging::PHYS_VIRT_OFFSET) as *mut u8,
+            0,
+            (count * PAGE_SIZE) as _,
         );
-
-        let mut bitmap: &mut [u64] = Default::default();
-
-        let mut free_pages = 0;
-
-        for v in mmap {
-            trace!("{v:X?}");
-
-            let MemoryEntry::Usable(v) = v else {
-                continue;
-            };
-
-            let v = {
-                if v.length == 0 {
-                    continue;
-                }
-                if v.base <= 0x20_0000 {
-                    if v.base + v.length > 0x20_0000 {
-                        MemoryData::new(0x20_0000, v.length - 0x20_0000)
-                    } else {
-                        continue;
-                    }
-                } else if bitmap.is_empty() && v.length >= bitmap_sz {
-                    bitmap = unsafe {
-                        core::slice::from_raw_parts_mut(
-                            (v.base + amd64::paging::PHYS_VIRT_OFFSET) as *mut _,
-                            (bitmap_sz / 8) as _,
-                        )
-                    };
-                    bitmap.fill(!0u64);
-
-                    trace!("Placing bitmap at {:#X}", v.base);
-                    MemoryData::new(
-                        (v.base + bitmap_sz + (PAGE_SIZE - 1)) & !PAGE_SIZE,
-                        (v.length - bitmap_sz + (PAGE_SIZE - 1)) & !PAGE_SIZE,
-                    )
-                } else {
-                    *v
-                }
-            };
-
-            let (base, count) = (v.base / PAGE_SIZE, v.length / PAGE_SIZE);
-            for i in base..(base + count) {
-                crate::bitmap::bit_reset(bitmap, i);
-            }
-            free_pages += count;
-        }
-
-        assert!(!bitmap.is_empty());
-
-        Self {
-            bitmap,
-            highest_addr,
-            free_pages,
-            last_index: 0,
-        }
-    }
-
-    unsafe fn internal_alloc(&mut self, count: u64, limit: u64) -> *mut u8 {
-        let mut n = 0;
-
-        while self.last_index < limit {
-            let set = crate::bitmap::bit_test(self.bitmap, self.last_index);
-            self.last_index += 1;
-
-            if set {
-                n = 0;
-                continue;
-            }
-
-            n += 1;
-
-            if n == count {
-                let page = self.last_index - count;
-
-                for i in page..self.last_index {
-                    crate::bitmap::bit_set(self.bitmap, i);
-                }
-
-                self.free_pages -= count;
-
-                return (page * PAGE_SIZE) as *mut _;
-            }
-        }
-
-        core::ptr::null_mut()
-    }
-
-    pub unsafe fn alloc(&mut self, count: u64) -> *mut u8 {
-        let l = self.last_index;
-
-        let ret = self.internal_alloc(count, self.highest_addr / PAGE_SIZE);
-        if ret.is_null() {
-            self.last_index = 0;
-            self.internal_alloc(count, l)
-        } else {
-            ret
-        }
     }
+    Ok(phys)
+}
 
-    pub unsafe fn free(&mut self, ptr: *mut u8, count: u64) {
-        assert_eq!(ptr as u64 & (PAGE_SIZE - 1), 0);
-
-        let idx = ptr as u64 / PAGE_SIZE;
-
-        for i in idx..(idx + count) {
-            crate::bitmap::bit_reset(self.bitmap, i);
-        }
+/// Returns `count` pages from [`alloc`].
+pub unsafe fn free(phys: u64, count: u64) {
+    let state = &*super::state::SYS_STATE.get();
+    state
+        .pmm
+        .as_ref()
+        .unwrap()
+        .lock()
+        .free(phys as *mut _, count);
+}
 
-        self.free_pages += count;
+pub fn new(mmap: &'static [MemoryEntry]) -> BuddyAllocator {
+    for v in mmap {
+        trace!("{v:X?}");
     }
 
-    pub fn is_allocated(&self, ptr: *mut u8, count: u64) -> bool {
-        assert_eq!(ptr as u64 & (PAGE_SIZE - 1), 0);
-
-        let idx = ptr as u64 / PAGE_SIZE;
-
-        for i in idx..(idx + count) {
-            if !crate::bitmap::bit_test(self.bitmap, i) {
-                return false;
+    let usable = mmap.iter().filter_map(|v| {
+        let MemoryEntry::Usable(v) = v else {
+            return None;
+        };
+        (v.length != 0).then_some((v.base, v.length))
+    });
+    // Leave room for the memory reclaimed later on.
+    let end = mmap
+        .iter()
+        .filter_map(|v| match v {
+            MemoryEntry::BootLoaderReclaimable(v) | MemoryEntry::ACPIReclaimable(v) => {
+                Some(v.base + v.length)
             }
-        }
-
-        true
+            _ => None,
+        })
+        .max()
+        .unwrap_or_default();
+    let pmm = unsafe { BuddyAllocator::new(usable, end, amd64::paging::PHYS_VIRT_OFFSET) };
+
+    trace!("Highest address: {:#X}", pmm.highest_addr);
+    for zone in Zone::ALL {
+        trace!("{zone:?}: {:?}", pmm.zone_stats(zone));
     }
+    pmm
+}
+
+/// Frees the memory of the bootloader and of the ACPI tables.
+///
+/// Nothing may refer to the boot info anymore, and the ACPI tables must have been copied.
+pub fn reclaim(mmap: &'static [MemoryEntry]) {
+    // The memory map itself is among what gets freed.
+    let (boot, acpi): (Vec<_>, Vec<_>) = mmap
+        .iter()
+        .filter_map(|v| match v {
+            MemoryEntry::BootLoaderReclaimable(v) => Some((true, v.base, v.length)),
+            MemoryEntry::ACPIReclaimable(v) => Some((false, v.base, v.length)),
+            _ => None,
+        })
+        .partition(|v| v.0);
+
+    let state = unsafe { &*super::state::SYS_STATE.get() };
+    let mut pmm = state.pmm.as_ref().unwrap().lock();
+    let boot_pages: u64 = boot
+        .iter()
+        .map(|&(_, base, len)| unsafe { pmm.add_range(base, len) })
+        .sum();
+    let acpi_pages: u64 = acpi
+        .iter()
+        .map(|&(_, base, len)| unsafe { pmm.add_range(base, len) })
+        .sum();
+    drop(pmm);
+
+    debug!(
+        "Reclaimed {} KiB of bootloader memory and {} KiB of ACPI memory",
+        boot_pages * PAGE_SIZE / 1024,
+        acpi_pages * PAGE_SIZE / 1024,
+    );
 }
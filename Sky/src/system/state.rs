@@ -1,13 +1,13 @@
 // Copyright (c) ChefKiss 2021-2025. Licensed under the Thou Shalt Not Profit License version 1.5. See LICENSE for details.
 
-use alloc::{boxed::Box, string::String, vec::Vec};
-use core::cell::SyncUnsafeCell;
+use alloc::{boxed::Box, collections::BTreeMap, string::String, vec::Vec};
+use core::{cell::SyncUnsafeCell, sync::atomic::AtomicU64};
 
 use hashbrown::HashMap;
 
-use super::{
-    pmm::BitmapAllocator, tasking::scheduler::Scheduler, terminal::Terminal, vmm::PageTableLvl4,
-};
+use skybuddy::BuddyAllocator;
+
+use super::{tasking::scheduler::Scheduler, terminal::Terminal, vmm::PageTableLvl4};
 use crate::{
     acpi::{apic::LocalAPIC, madt::MADTData, ACPIState},
     incr_id::IncrementalIDGen,
@@ -21,12 +21,30 @@ pub struct OSDTEntry {
     pub id: u64,
     pub properties: HashMap<String, skykit::osvalue::OSValue>,
     pub children: Vec<skykit::osdtentry::OSDTEntry>,
+    pub owner_pid: Option<u64>,
+    pub attached_pid: Option<u64>,
+    pub live: Option<super::osdt::LiveSource>,
+}
+
+impl OSDTEntry {
+    #[inline]
+    pub fn is_modifiable_by(&self, pid: u64) -> bool {
+        self.owner_pid == Some(pid) || self.attached_pid == Some(pid)
+    }
 }
 
 pub struct SystemState {
     pub verbose: bool,
     pub serial_enabled: bool,
-    pub pmm: Option<spin::Mutex<BitmapAllocator>>,
+    pub snapshot_on_idle: bool,
+    /// Kill the process using the most memory when an allocation fails, instead of failing it.
+    pub oom_kill: bool,
+    /// Allocations the PMM could not satisfy.
+    pub alloc_failures: AtomicU64,
+    pub oom_kills: AtomicU64,
+    pub pmm: Option<spin::Mutex<BuddyAllocator>>,
+    /// Owner count of the frames referenced by more than one area.
+    pub frame_refs: spin::Mutex<BTreeMap<u64, u64>>,
     pub pml4: Option<spin::Mutex<Box<PageTableLvl4>>>,
     pub terminal: Option<Terminal>,
     pub acpi: Option<ACPIState>,
@@ -52,7 +70,12 @@ impl SystemState {
         Self {
             verbose: cfg!(debug_assertions),
             serial_enabled: false,
+            snapshot_on_idle: false,
+            oom_kill: false,
+            alloc_failures: AtomicU64::new(0),
+            oom_kills: AtomicU64::new(0),
             pmm: None,
+            frame_refs: spin::Mutex::new(BTreeMap::new()),
             pml4: None,
             terminal: None,
             acpi: None,
@@ -0,0 +1,404 @@
+// Copyright (c) ChefKiss 2021-2025. Licensed under the Thou Shalt Not Profit License version 1.5. See LICENSE for details.
+
+use alloc::{string::String, vec::Vec};
+use core::{
+    fmt,
+    ops::{Deref, DerefMut, Range},
+};
+
+use amd64::paging::{PAGE_SIZE, PHYS_VIRT_OFFSET};
+use elf::{
+    abi::{
+        DT_NEEDED, EM_X86_64, ET_DYN, PF_W, PF_X, PT_GNU_RELRO, PT_LOAD, R_X86_64_64,
+        R_X86_64_GLOB_DAT, R_X86_64_JUMP_SLOT, R_X86_64_NONE, R_X86_64_RELATIVE, SHN_ABS, SHT_RELA,
+        STB_LOCAL, STB_WEAK,
+    },
+    endian::NativeEndian,
+    file::Class,
+    string_table::StringTable,
+    symbol::{Symbol, SymbolTable},
+    ElfBytes, ParseError,
+};
+use hashbrown::HashMap;
+use skykit::syscall::{ABI_NOTE_NAME, ABI_VERSION, NT_SKYKIT_ABI};
+
+use super::{vma::USER_BASE, AllocationType};
+use crate::system::pmm;
+
+#[derive(Debug)]
+pub enum SpawnError {
+    Malformed(ParseError),
+    UnsupportedImage,
+    ABIMismatch(Option<u32>),
+    NoLoadableSegments,
+    SegmentOutOfBounds,
+    WritableExecutableSegment,
+    EntryNotExecutable,
+    RelocationOutOfBounds(u64),
+    UnsupportedRelocation(u32),
+    UndefinedSymbol(String),
+    MissingLibrary(String),
+    OutOfMemory,
+}
+
+impl From<ParseError> for SpawnError {
+    fn from(value: ParseError) -> Self {
+        Self::Malformed(value)
+    }
+}
+
+impl fmt::Display for SpawnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed(e) => write!(f, "Malformed ELF: {e}"),
+            Self::UnsupportedImage => write!(f, "Not an x86_64 position-independent executable"),
+            Self::ABIMismatch(Some(v)) => {
+                write!(f, "Built against ABI version {v}, expected {ABI_VERSION}")
+            }
+            Self::ABIMismatch(None) => write!(f, "Missing SkyKit ABI note"),
+            Self::NoLoadableSegments => write!(f, "No loadable segments"),
+            Self::SegmentOutOfBounds => write!(f, "Segment out of bounds"),
+            Self::WritableExecutableSegment => write!(f, "Writable and executable segment"),
+            Self::EntryNotExecutable => write!(f, "Entry point is not in an executable segment"),
+            Self::RelocationOutOfBounds(v) => write!(f, "Relocation at {v:#X} out of bounds"),
+            Self::UnsupportedRelocation(v) => write!(f, "Unsupported relocation type {v:#X}"),
+            Self::UndefinedSymbol(v) => write!(f, "Undefined symbol {v}"),
+            Self::MissingLibrary(v) => write!(f, "Missing library {v}"),
+            Self::OutOfMemory => write!(f, "Out of memory"),
+        }
+    }
+}
+
+/// An object mapped into a process, see [`Image`].
+pub struct Object {
+    pub base: u64,
+    pub phys: u64,
+    /// Mapping of every page, [`AllocationType::Kernel`] for the ones no segment covers.
+    pub page_types: Vec<AllocationType>,
+}
+
+/// An extension and the libraries it needs, relocated in place and ready to be tracked by its
+/// process.
+pub struct Image {
+    /// The executable comes first, followed by its libraries in load order.
+    pub objects: Vec<Object>,
+    pub entry: u64,
+}
+
+/// Pages taken from the PMM rather than the heap, as they are freed to it with the areas they
+/// end up backing.
+struct Pages {
+    phys: u64,
+    len: usize,
+}
+
+impl Pages {
+    fn new(len: u64) -> Result<Self, SpawnError> {
+        let phys = pmm::alloc_zeroed(len / PAGE_SIZE).map_err(|_| SpawnError::OutOfMemory)?;
+        Ok(Self {
+            phys,
+            len: len as usize,
+        })
+    }
+
+    /// Gives up ownership of the pages, returning their address.
+    const fn leak(self) -> u64 {
+        let phys = self.phys;
+        core::mem::forget(self);
+        phys
+    }
+}
+
+impl Deref for Pages {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        unsafe {
+            core::slice::from_raw_parts((self.phys + PHYS_VIRT_OFFSET) as *const u8, self.len)
+        }
+    }
+}
+
+impl DerefMut for Pages {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe {
+            core::slice::from_raw_parts_mut((self.phys + PHYS_VIRT_OFFSET) as *mut u8, self.len)
+        }
+    }
+}
+
+impl Drop for Pages {
+    fn drop(&mut self) {
+        unsafe { pmm::free(self.phys, self.len as u64 / PAGE_SIZE) }
+    }
+}
+
+type DynamicSymbols<'a> = Option<(SymbolTable<'a, NativeEndian>, StringTable<'a>)>;
+
+struct LoadingObject<'a> {
+    elf: ElfBytes<'a, NativeEndian>,
+    symbols: DynamicSymbols<'a>,
+    abi_version: Option<u32>,
+    data: Pages,
+    base: u64,
+    page_types: Vec<AllocationType>,
+    relro: Vec<Range<usize>>,
+}
+
+impl<'a> LoadingObject<'a> {
+    fn new(bytes: &'a [u8], base: u64) -> Result<Self, SpawnError> {
+        let elf = ElfBytes::<NativeEndian>::minimal_parse(bytes)?;
+        if elf.ehdr.e_type != ET_DYN
+            || elf.ehdr.class != Class::ELF64
+            || elf.ehdr.e_machine != EM_X86_64
+        {
+            return Err(SpawnError::UnsupportedImage);
+        }
+
+        let segments = elf.segments().ok_or(SpawnError::NoLoadableSegments)?;
+
+        let abi_version = segments
+            .iter()
+            .filter_map(|v| elf.segment_data_as_notes(&v).ok())
+            .flatten()
+            .find_map(|v| match v {
+                elf::note::Note::Unknown(v)
+                    if v.n_type == NT_SKYKIT_ABI && v.name == ABI_NOTE_NAME =>
+                {
+                    v.desc.try_into().ok().map(u32::from_ne_bytes)
+                }
+                _ => None,
+            });
+
+        let mut len = 0;
+        for hdr in segments.iter().filter(|v| v.p_type == PT_LOAD) {
+            hdr.p_offset
+                .checked_add(hdr.p_filesz)
+                .filter(|&v| v <= bytes.len() as u64 && hdr.p_filesz <= hdr.p_memsz)
+                .ok_or(SpawnError::SegmentOutOfBounds)?;
+            let end = hdr
+                .p_vaddr
+                .checked_add(hdr.p_memsz)
+                .ok_or(SpawnError::SegmentOutOfBounds)?;
+            len = len.max(end);
+        }
+        if len == 0 {
+            return Err(SpawnError::NoLoadableSegments);
+        }
+        let len = len
+            .checked_next_multiple_of(PAGE_SIZE)
+            .ok_or(SpawnError::SegmentOutOfBounds)?;
+
+        let mut page_types = vec![AllocationType::Kernel; (len / PAGE_SIZE) as usize];
+        for hdr in segments.iter().filter(|v| v.p_type == PT_LOAD) {
+            let ty = match (hdr.p_flags & PF_W != 0, hdr.p_flags & PF_X != 0) {
+                (true, true) => return Err(SpawnError::WritableExecutableSegment),
+                (true, false) => AllocationType::Writable,
+                (false, true) => AllocationType::Executable,
+                (false, false) => AllocationType::Readable,
+            };
+            let pages = hdr.p_vaddr / PAGE_SIZE..(hdr.p_vaddr + hdr.p_memsz).div_ceil(PAGE_SIZE);
+            for page in &mut page_types[pages.start as usize..pages.end as usize] {
+                *page = match *page {
+                    AllocationType::Kernel | AllocationType::Readable => ty,
+                    v if v == ty || ty == AllocationType::Readable => v,
+                    _ => return Err(SpawnError::WritableExecutableSegment),
+                };
+            }
+        }
+
+        let mut relro = Vec::new();
+        for hdr in segments.iter().filter(|v| v.p_type == PT_GNU_RELRO) {
+            let end = hdr
+                .p_vaddr
+                .checked_add(hdr.p_memsz)
+                .filter(|&v| v <= len)
+                .ok_or(SpawnError::SegmentOutOfBounds)?;
+            relro.push((hdr.p_vaddr / PAGE_SIZE) as usize..(end / PAGE_SIZE) as usize);
+        }
+
+        let mut data = Pages::new(len)?;
+        for hdr in segments.iter().filter(|v| v.p_type == PT_LOAD) {
+            let fsz = hdr.p_filesz as usize;
+            let foff = hdr.p_offset as usize;
+            let ext_vaddr = hdr.p_vaddr as usize;
+            data[ext_vaddr..ext_vaddr + fsz].copy_from_slice(&bytes[foff..foff + fsz]);
+        }
+
+        Ok(Self {
+            symbols: elf.dynamic_symbol_table()?,
+            elf,
+            abi_version,
+            base,
+            data,
+            page_types,
+            relro,
+        })
+    }
+
+    fn needed(&self) -> Result<Vec<&'a str>, SpawnError> {
+        let Some(dynamic) = self.elf.dynamic()? else {
+            return Ok(Vec::new());
+        };
+        let Some((_, strtab)) = &self.symbols else {
+            return Err(SpawnError::UnsupportedImage);
+        };
+        dynamic
+            .iter()
+            .filter(|v| v.d_tag == DT_NEEDED)
+            .map(|v| Ok(strtab.get(v.d_val() as usize)?))
+            .collect()
+    }
+
+    const fn resolve(&self, sym: &Symbol) -> u64 {
+        if sym.st_shndx == SHN_ABS {
+            sym.st_value
+        } else {
+            self.base.wrapping_add(sym.st_value)
+        }
+    }
+
+    fn symbol_value(&self, index: u32, globals: &HashMap<&str, u64>) -> Result<u64, SpawnError> {
+        if index == 0 {
+            return Ok(0);
+        }
+        let Some((symtab, strtab)) = &self.symbols else {
+            return Err(SpawnError::UnsupportedImage);
+        };
+        let sym = symtab.get(index as usize)?;
+        if sym.st_bind() == STB_LOCAL && !sym.is_undefined() {
+            return Ok(self.resolve(&sym));
+        }
+        let name = strtab.get(sym.st_name as usize)?;
+        if let Some(&v) = globals.get(name) {
+            return Ok(v);
+        }
+        if sym.st_bind() == STB_WEAK {
+            return Ok(0);
+        }
+        Err(SpawnError::UndefinedSymbol(name.into()))
+    }
+
+    fn relocate(&mut self, globals: &HashMap<&str, u64>) -> Result<(), SpawnError> {
+        for shdr in self
+            .elf
+            .section_headers()
+            .into_iter()
+            .flatten()
+            .filter(|v| v.sh_type == SHT_RELA)
+        {
+            for reloc in self.elf.section_data_as_relas(&shdr)? {
+                let value = match reloc.r_type {
+                    R_X86_64_NONE => continue,
+                    R_X86_64_RELATIVE => self.base.wrapping_add_signed(reloc.r_addend),
+                    R_X86_64_64 => self
+                        .symbol_value(reloc.r_sym, globals)?
+                        .wrapping_add_signed(reloc.r_addend),
+                    R_X86_64_GLOB_DAT | R_X86_64_JUMP_SLOT => {
+                        self.symbol_value(reloc.r_sym, globals)?
+                    }
+                    v => return Err(SpawnError::UnsupportedRelocation(v)),
+                };
+                usize::try_from(reloc.r_offset)
+                    .ok()
+                    .and_then(|off| self.data.get_mut(off..off.checked_add(8)?))
+                    .ok_or(SpawnError::RelocationOutOfBounds(reloc.r_offset))?
+                    .copy_from_slice(&value.to_ne_bytes());
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Object {
+        for pages in self.relro {
+            for page in &mut self.page_types[pages] {
+                if *page == AllocationType::Writable {
+                    *page = AllocationType::Readable;
+                }
+            }
+        }
+
+        // The pages are owned by the allocations tracked from `page_types` from here on.
+        Object {
+            base: self.base,
+            phys: self.data.leak(),
+            page_types: self.page_types,
+        }
+    }
+}
+
+impl Image {
+    /// Loads an executable and, breadth first, the libraries it needs out of `libraries`.
+    ///
+    /// The objects are laid out back to back from [`USER_BASE`], as the address space of the
+    /// process they are for is still empty.
+    ///
+    /// Every symbol is bound eagerly, the executable's definitions taking precedence over those of
+    /// its libraries.
+    pub fn load(
+        exec_data: &[u8],
+        libraries: &HashMap<String, Vec<u8>>,
+    ) -> Result<Self, SpawnError> {
+        let mut objects = vec![LoadingObject::new(exec_data, USER_BASE)?];
+        let entry = objects[0].elf.ehdr.e_entry;
+        if objects[0].page_types.get((entry / PAGE_SIZE) as usize)
+            != Some(&AllocationType::Executable)
+        {
+            return Err(SpawnError::EntryNotExecutable);
+        }
+
+        let mut loaded = Vec::new();
+        let mut i = 0;
+        while i < objects.len() {
+            for name in objects[i].needed()? {
+                if loaded.contains(&name) {
+                    continue;
+                }
+                let Some(bytes) = libraries.get(name) else {
+                    return Err(SpawnError::MissingLibrary(name.into()));
+                };
+                let last = objects.last().unwrap();
+                let base = last.base + last.data.len() as u64;
+                objects.push(LoadingObject::new(bytes, base)?);
+                loaded.push(name);
+            }
+            i += 1;
+        }
+
+        // System calls may be made from any of the objects, so all that carry a note must agree.
+        if let Some(v) = objects
+            .iter()
+            .find_map(|v| v.abi_version.filter(|&v| v != ABI_VERSION))
+        {
+            return Err(SpawnError::ABIMismatch(Some(v)));
+        }
+        if objects.iter().all(|v| v.abi_version.is_none()) {
+            return Err(SpawnError::ABIMismatch(None));
+        }
+
+        let mut globals = HashMap::new();
+        for object in &objects {
+            let Some((symtab, strtab)) = &object.symbols else {
+                continue;
+            };
+            for sym in symtab
+                .iter()
+                .filter(|v| !v.is_undefined() && v.st_bind() != STB_LOCAL)
+            {
+                globals
+                    .entry(strtab.get(sym.st_name as usize)?)
+                    .or_insert_with(|| object.resolve(&sym));
+            }
+        }
+
+        for object in &mut objects {
+            object.relocate(&globals)?;
+        }
+
+        let base = objects[0].base;
+        Ok(Self {
+            objects: objects.into_iter().map(LoadingObject::finish).collect(),
+            entry: base + entry,
+        })
+    }
+}
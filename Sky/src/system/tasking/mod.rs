@@ -1,17 +1,31 @@
 // Copyright (c) ChefKiss 2021-2025. Licensed under the Thou Shalt Not Profit License version 1.5. See LICENSE for details.
 
-use alloc::{boxed::Box, collections::VecDeque, string::String, vec::Vec};
-
-use amd64::paging::{PageTableFlags, PAGE_SIZE};
+use alloc::{
+    boxed::Box,
+    collections::{btree_map, VecDeque},
+    string::String,
+    vec::Vec,
+};
+
+use amd64::paging::{PageTableFlags, PAGE_MASK, PAGE_SIZE};
 use hashbrown::{HashMap, HashSet};
 use skykit::msg::Message;
 
-use super::gdt::{PrivilegeLevel, SegmentSelector};
+use self::vma::{AddressSpace, Backing, VirtualArea};
+use super::{
+    gdt::{PrivilegeLevel, SegmentSelector},
+    pmm::OutOfMemory,
+};
 
+pub mod image;
 pub mod scheduler;
 pub mod userland;
+pub mod vma;
 
-pub const STACK_LEN: u64 = 0x14000;
+/// Address space reserved for every user stack, backed as it grows.
+pub const STACK_LEN: u64 = 0x80_0000;
+/// Inaccessible area right below every user stack, catching overflows.
+pub const STACK_GUARD_LEN: u64 = PAGE_SIZE;
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum ThreadState {
@@ -41,26 +55,30 @@ pub struct Thread {
     pub fs_base: usize,
     pub gs_base: usize,
     pub stack_addr: u64,
+    pub cpu_time: u64,
+    pub dt_entry: u64,
 }
 
 impl Thread {
     #[inline]
-    fn new(id: u64, pid: u64, rip: u64, stack_addr: u64) -> Self {
+    fn new(id: u64, pid: u64, rip: u64, stack_addr: u64, dt_entry: u64) -> Self {
         Self {
             id,
             pid,
             state: ThreadState::Inactive,
             regs: super::RegisterState {
                 rip,
-                cs: SegmentSelector::new(3, PrivilegeLevel::User).into(),
+                cs: SegmentSelector::new(4, PrivilegeLevel::User).into(),
                 rflags: 0x202,
                 rsp: stack_addr + STACK_LEN,
-                ss: SegmentSelector::new(4, PrivilegeLevel::User).into(),
+                ss: SegmentSelector::new(3, PrivilegeLevel::User).into(),
                 ..Default::default()
             },
             fs_base: 0,
             gs_base: 0,
             stack_addr,
+            cpu_time: 0,
+            dt_entry,
         }
     }
 }
@@ -70,6 +88,44 @@ pub enum AllocationType {
     Kernel,
     Readable,
     Writable,
+    Executable,
+    /// Reserved by userspace but not accessible.
+    Guard,
+}
+
+impl AllocationType {
+    #[inline]
+    pub const fn is_accessible(self) -> bool {
+        !matches!(self, Self::Kernel | Self::Guard)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AllocError {
+    OutOfMemory,
+    LimitExceeded,
+}
+
+impl From<OutOfMemory> for AllocError {
+    fn from(_: OutOfMemory) -> Self {
+        Self::OutOfMemory
+    }
+}
+
+/// Pages used by a process, by kind.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MemoryUsage {
+    /// Backed pages no other process references.
+    pub private: u64,
+    /// Backed pages referenced by other processes too, messages aside.
+    pub shared: u64,
+    /// Pages of the messages sent or received.
+    pub message: u64,
+    /// Frames of the page tables, allocated by the kernel.
+    pub page_tables: u64,
+    /// Pages of the process' own areas, backed or not, counted against its limit. Stacks only
+    /// count their backed pages, the space reserved for them is not.
+    pub committed: u64,
 }
 
 #[derive(Debug)]
@@ -79,137 +135,377 @@ pub struct Process {
     pub image_base: u64,
     pub cr3: spin::Mutex<Box<userland::page_table::UserPML4>>,
     pub messages: VecDeque<Message>,
-    pub allocations: HashMap<u64, (u64, AllocationType)>,
+    pub vmas: AddressSpace,
     pub msg_id_to_addr: HashMap<u64, u64>,
     pub addr_to_msg_id: HashMap<u64, u64>,
+    /// Where messages from other processes are mapped, keyed by message ID.
+    pub msg_mappings: HashMap<u64, u64>,
     pub thread_ids: HashSet<u64>,
+    /// Bottoms of the threads' stacks, see [`MemoryUsage::committed`].
+    pub stacks: HashSet<u64>,
     pub alloc_lock: spin::Mutex<()>,
+    pub dt_entry: u64,
+    /// Bytes the process' own areas may span, see [`MemoryUsage::committed`]. Reserving areas
+    /// past it fails, stacks growing past it do not.
+    pub memory_limit: Option<u64>,
 }
 
 impl Process {
     #[inline]
-    pub fn new(id: u64, path: String, image_base: u64) -> Self {
+    pub fn new(
+        id: u64,
+        path: String,
+        image_base: u64,
+        dt_entry: u64,
+        memory_limit: Option<u64>,
+    ) -> Self {
         Self {
             id,
             path,
             image_base,
-            cr3: Box::new(userland::page_table::UserPML4::new(id)).into(),
+            cr3: Box::new(userland::page_table::UserPML4::new()).into(),
             messages: VecDeque::new(),
-            allocations: HashMap::new(),
+            vmas: AddressSpace::new(),
             msg_id_to_addr: HashMap::new(),
             addr_to_msg_id: HashMap::new(),
+            msg_mappings: HashMap::new(),
             thread_ids: HashSet::new(),
+            stacks: HashSet::new(),
             alloc_lock: spin::Mutex::new(()),
+            dt_entry,
+            memory_limit,
         }
     }
 
     #[inline]
-    pub fn new_thread(&mut self, id: u64, rip: u64, stack_addr: u64) -> Thread {
-        let thread = Thread::new(id, self.id, rip, stack_addr);
+    pub fn new_thread(&mut self, id: u64, rip: u64, stack_addr: u64, dt_entry: u64) -> Thread {
+        let thread = Thread::new(id, self.id, rip, stack_addr, dt_entry);
         self.thread_ids.insert(id);
         thread
     }
 
-    pub fn track_alloc(&mut self, addr: u64, len: u64, ty: AllocationType) {
+    /// Tracks `vma` at `addr`, mapping it unless it is backed on demand. The area is tracked even
+    /// if there is no memory left to map it, the pages left unmapped are mapped as they are
+    /// accessed.
+    pub fn track_alloc(&mut self, addr: u64, vma: VirtualArea) -> Result<(), OutOfMemory> {
         let _lock = self.alloc_lock.lock();
 
-        if self.allocations.contains_key(&addr) {
-            panic!("PID {}: Address {addr:#X} already allocated", self.id);
+        let page_count = vma.page_count();
+
+        if let Backing::Contiguous(phys) = vma.backing {
+            assert!(
+                unsafe {
+                    (*crate::system::state::SYS_STATE.get())
+                        .pmm
+                        .as_ref()
+                        .unwrap()
+                        .lock()
+                        .is_allocated(phys as *mut _, page_count)
+                },
+                "PID {}: Address {phys:#X} not allocated",
+                self.id,
+            );
         }
 
-        let page_count = len.div_ceil(PAGE_SIZE);
-
-        assert!(
-            unsafe {
-                (*crate::system::state::SYS_STATE.get())
-                    .pmm
-                    .as_ref()
-                    .unwrap()
-                    .lock()
-                    .is_allocated((addr - skykit::USER_VIRT_OFFSET) as *mut _, page_count)
-            },
-            "PID {}: Address {addr:#X} not allocated",
-            self.id,
-        );
-
         trace!(
-            "PID {}: Tracking {addr:#X} ({ty:?}, {len} byte{}, {page_count} page{}, will map: {})",
+            "PID {}: Tracking {addr:#X} ({:?}, {} byte{}, {page_count} page{}, will map: {})",
             self.id,
-            if len > 1 { "s" } else { "" },
+            vma.ty,
+            vma.len,
+            if vma.len > 1 { "s" } else { "" },
             if page_count > 1 { "s" } else { "" },
-            ty != AllocationType::Kernel
+            vma.ty.is_accessible()
         );
-        self.allocations.insert(addr, (len, ty));
 
-        if ty == AllocationType::Kernel {
-            return;
+        let flags = Self::page_flags(vma.ty, false);
+        let mapping = match &vma.backing {
+            _ if !vma.ty.is_accessible() => Vec::new(),
+            Backing::Contiguous(phys) => alloc::vec![(addr, *phys, page_count)],
+            Backing::OnDemand(_) => Vec::new(),
+            Backing::Shared(frames) => frames
+                .iter()
+                .enumerate()
+                .map(|(i, &phys)| (addr + i as u64 * PAGE_SIZE, phys, 1))
+                .collect(),
+        };
+
+        if !self.vmas.insert(addr, vma) {
+            panic!("PID {}: Address {addr:#X} already allocated", self.id);
+        }
+
+        drop(_lock);
+        let mut cr3 = self.cr3.lock();
+        for (virt, phys, count) in mapping {
+            unsafe { cr3.map(virt, phys, count, flags)? }
+        }
+        Ok(())
+    }
+
+    fn page_flags(ty: AllocationType, copy_on_write: bool) -> PageTableFlags {
+        PageTableFlags::new_present()
+            .with_writable(ty == AllocationType::Writable && !copy_on_write)
+            .with_user(true)
+            .with_executable(ty == AllocationType::Executable)
+            .with_copy_on_write(copy_on_write)
+    }
+
+    /// Backs the page containing `addr` if it is part of an on-demand area and was never accessed,
+    /// or maps it if it is backed but was left unmapped.
+    pub fn fault_in(&self, addr: u64) -> Result<bool, OutOfMemory> {
+        let page = addr & !PAGE_MASK;
+        let Some((start, vma)) = self.vmas.containing(page, 1) else {
+            return Ok(false);
+        };
+        if !vma.ty.is_accessible() {
+            return Ok(false);
+        }
+        let mut cr3 = self.cr3.lock();
+        if unsafe { cr3.virt_to_phys(page) }.is_some() {
+            return Ok(false);
+        }
+        if let Some(phys) = vma.frame((page - start) / PAGE_SIZE) {
+            let flags = Self::page_flags(vma.ty, vma.copy_on_write(phys));
+            unsafe { cr3.map(page, phys, 1, flags)? }
+            return Ok(true);
+        }
+        let Backing::OnDemand(frames) = &vma.backing else {
+            return Ok(false);
+        };
+        let mut frames = frames.lock();
+        let btree_map::Entry::Vacant(frame) = frames.entry((page - start) / PAGE_SIZE) else {
+            return Ok(false);
+        };
+
+        let phys = super::pmm::alloc_zeroed(1)?;
+        if let Err(e) = unsafe { cr3.map(page, phys, 1, Self::page_flags(vma.ty, false)) } {
+            unsafe { super::pmm::free(phys, 1) }
+            return Err(e);
+        }
+        frame.insert(phys);
+        Ok(true)
+    }
+
+    /// Gives the page containing `addr` a frame of its own if it is marked copy-on-write.
+    pub fn copy_on_write(&self, addr: u64) -> Result<bool, OutOfMemory> {
+        let page = addr & !PAGE_MASK;
+        let Some((start, vma)) = self.vmas.containing(page, 1) else {
+            return Ok(false);
+        };
+        let Backing::OnDemand(frames) = &vma.backing else {
+            return Ok(false);
+        };
+        let mut cr3 = self.cr3.lock();
+        if !unsafe { cr3.virt_to_phys(page) }.is_some_and(|(_, v)| v.copy_on_write) {
+            return Ok(false);
+        }
+        let mut frames = frames.lock();
+        let frame = frames.get_mut(&((page - start) / PAGE_SIZE)).unwrap();
+
+        // The other owners may have let go of the frame since.
+        if vma::is_shared(*frame) {
+            let phys = super::pmm::alloc(1)?;
+            unsafe {
+                core::ptr::copy_nonoverlapping(
+                    (*frame + amd64::paging::PHYS_VIRT_OFFSET) as *const u8,
+                    (phys + amd64::paging::PHYS_VIRT_OFFSET) as *mut u8,
+                    PAGE_SIZE as _,
+                );
+            }
+            assert!(!vma::unshare_frame(*frame));
+            *frame = phys;
+        }
+        unsafe { cr3.remap(page, *frame, Self::page_flags(vma.ty, false)) }
+        Ok(true)
+    }
+
+    /// Backs every page of a range the kernel is about to read. Pages marked copy-on-write keep
+    /// sharing their frame.
+    pub fn populate(&self, addr: u64, len: u64) -> Result<(), OutOfMemory> {
+        for page in ((addr & !PAGE_MASK)..addr + len).step_by(PAGE_SIZE as _) {
+            self.fault_in(page)?;
         }
+        Ok(())
+    }
+
+    /// References the frames backing `addr..addr + len`, which must be backed, making the
+    /// pages copy-on-write so the owner cannot change them under the new user.
+    pub fn share(&self, addr: u64, len: u64) -> Vec<u64> {
+        let (start, vma) = self.vmas.containing_pages(addr, len).unwrap();
+        let first = (addr - start) / PAGE_SIZE;
+        let mut cr3 = self.cr3.lock();
+        (first..first + len.div_ceil(PAGE_SIZE))
+            .map(|i| {
+                let phys = vma.frame(i).unwrap();
+                vma::share_frame(phys);
+                let virt = start + i * PAGE_SIZE;
+                if vma.copy_on_write(phys) && unsafe { cr3.virt_to_phys(virt) }.is_some() {
+                    unsafe { cr3.remap(virt, phys, Self::page_flags(vma.ty, true)) }
+                }
+                phys
+            })
+            .collect()
+    }
+
+    /// Maps `vma` anywhere in the address space, the caller keeping its frames if there is no room.
+    pub fn map_anywhere(&mut self, vma: VirtualArea) -> Result<u64, OutOfMemory> {
+        let addr = self.vmas.find_free(vma.len).ok_or(OutOfMemory)?;
+        self.track_alloc(addr, vma)?;
+        Ok(addr)
+    }
 
+    /// Maps a read-only copy of `data` on pages of its own, heap allocations may share theirs.
+    pub fn track_kernelside_alloc(&mut self, data: &[u8]) -> Result<u64, AllocError> {
+        let len = data.len() as u64;
+        self.check_limit(len)?;
+        let addr = self.vmas.find_free(len).ok_or(OutOfMemory)?;
+        let phys = super::pmm::alloc_zeroed(len.div_ceil(PAGE_SIZE))?;
         unsafe {
-            drop(_lock);
-            self.cr3.lock().map(
-                addr,
-                addr - skykit::USER_VIRT_OFFSET,
-                page_count,
-                PageTableFlags::new_present()
-                    .with_writable(ty == AllocationType::Writable)
-                    .with_user(true),
-            );
+            ((phys + amd64::paging::PHYS_VIRT_OFFSET) as *mut u8)
+                .copy_from_nonoverlapping(data.as_ptr(), data.len());
+        }
+        if let Err(e) =
+            self.track_alloc(addr, VirtualArea::new(len, phys, AllocationType::Readable))
+        {
+            self.free_alloc(addr);
+            return Err(e.into());
         }
+        Ok(addr)
     }
 
-    pub fn track_kernelside_alloc(&mut self, addr: u64, len: u64) -> u64 {
-        let _lock = self.alloc_lock.lock();
+    /// Pages backed by a frame, including those shared with other processes.
+    pub fn resident_pages(&self) -> u64 {
+        self.vmas.areas().map(|(_, v)| v.resident_pages()).sum()
+    }
 
-        let addr = addr - amd64::paging::PHYS_VIRT_OFFSET + skykit::USER_VIRT_OFFSET;
-        drop(_lock);
-        self.track_alloc(addr, len, AllocationType::Readable);
-        addr
+    /// Areas mapped from other processes are not the process' own, guards are never backed and
+    /// stacks only count the pages they use.
+    fn committed_pages(&self) -> u64 {
+        self.vmas
+            .areas()
+            .filter(|(_, v)| {
+                v.ty != AllocationType::Guard && !matches!(v.backing, Backing::Shared(_))
+            })
+            .map(|(addr, v)| {
+                if self.stacks.contains(&addr) {
+                    v.resident_pages()
+                } else {
+                    v.page_count()
+                }
+            })
+            .sum()
+    }
+
+    /// Fails if `len` more bytes would take the process' own areas over its limit.
+    pub fn check_limit(&self, len: u64) -> Result<(), AllocError> {
+        let Some(limit) = self.memory_limit else {
+            return Ok(());
+        };
+        if (self.committed_pages() + len.div_ceil(PAGE_SIZE)) * PAGE_SIZE > limit {
+            trace!(
+                "PID {}: Allocating {len} bytes would exceed the limit",
+                self.id
+            );
+            return Err(AllocError::LimitExceeded);
+        }
+        Ok(())
+    }
+
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let messages: HashSet<u64> = self
+            .addr_to_msg_id
+            .keys()
+            .chain(self.msg_mappings.values())
+            .copied()
+            .collect();
+        let mut usage = MemoryUsage {
+            page_tables: self.cr3.lock().frame_count(),
+            committed: self.committed_pages(),
+            ..Default::default()
+        };
+        for (addr, vma) in self.vmas.areas() {
+            if messages.contains(&addr) {
+                usage.message += vma.resident_pages();
+                continue;
+            }
+            match &vma.backing {
+                Backing::Contiguous(_) => usage.private += vma.page_count(),
+                Backing::OnDemand(frames) => {
+                    for &phys in frames.lock().values() {
+                        if vma::is_shared(phys) {
+                            usage.shared += 1;
+                        } else {
+                            usage.private += 1;
+                        }
+                    }
+                }
+                Backing::Shared(frames) => usage.shared += frames.len() as u64,
+            }
+        }
+        usage
     }
 
-    pub fn region_is_valid(&self, addr: u64, len: u64) -> bool {
-        self.allocations
-            .iter()
-            .any(|(k, (v, _))| k <= &addr && addr + len <= k + v)
+    pub fn user_allocation(&self, addr: u64, len: u64) -> Option<AllocationType> {
+        self.vmas
+            .containing(addr, len)
+            .map(|(_, v)| v.ty)
+            .filter(|v| v.is_accessible())
     }
 
     pub fn region_is_within_bounds(&self, addr: u64, len: u64) -> bool {
-        self.allocations.get(&addr).is_some_and(|(v, _)| v >= &len)
+        self.vmas.get(addr).is_some_and(|v| v.len >= len)
     }
 
     pub fn region_is_mapped(&self, addr: u64, len: u64) -> bool {
-        self.allocations.get(&addr).is_some_and(|(v, _)| v == &len)
+        self.vmas
+            .get(addr)
+            .is_some_and(|v| !matches!(v.backing, Backing::Shared(_)) && v.len == len)
     }
 
     pub fn free_alloc(&mut self, addr: u64) {
         let _lock = self.alloc_lock.lock();
 
-        let (len, ty) = self.allocations.remove(&addr).unwrap();
-        let page_count = len.div_ceil(PAGE_SIZE);
+        let vma = self.vmas.remove(addr).unwrap();
+        let page_count = vma.page_count();
         trace!(
-            "PID {}: Freeing {addr:#X} ({ty:?}, {page_count} pages, {len} bytes)",
-            self.id
+            "PID {}: Freeing {addr:#X} ({:?}, {page_count} pages, {} bytes)",
+            self.id,
+            vma.ty,
+            vma.len,
         );
 
-        unsafe {
+        let mut cr3 = self.cr3.lock();
+        let mapped = vma.ty.is_accessible();
+        let frames: Vec<_> = match vma.backing {
+            Backing::Contiguous(phys) => (0..page_count).map(|i| phys + i * PAGE_SIZE).collect(),
+            Backing::OnDemand(frames) => frames.into_inner().into_values().collect(),
+            Backing::Shared(frames) => frames,
+        };
+        if mapped {
+            unsafe { cr3.unmap_range(addr, page_count) }
+        }
+        drop(cr3);
+
+        // Dropping references may free memory, so the PMM cannot be locked before.
+        let frames: Vec<_> = frames
+            .into_iter()
+            .filter(|&v| vma::unshare_frame(v))
+            .collect();
+        let mut pmm = unsafe {
             (*crate::system::state::SYS_STATE.get())
                 .pmm
                 .as_ref()
                 .unwrap()
                 .lock()
-                .free((addr - skykit::USER_VIRT_OFFSET) as *mut _, page_count);
-        }
-
-        if ty != AllocationType::Kernel {
-            drop(_lock);
-            unsafe { self.cr3.lock().unmap(addr, page_count) }
+        };
+        for phys in frames {
+            unsafe { pmm.free(phys as *mut _, 1) }
         }
     }
 
     pub fn track_msg(&mut self, id: u64, addr: u64) {
         let _lock = self.alloc_lock.lock();
 
-        if !self.allocations.contains_key(&addr) {
+        if self.vmas.get(addr).is_none() {
             panic!("PID {}: Address {addr:#X} not allocated", self.id);
         }
 
@@ -233,42 +529,71 @@ impl Process {
     pub fn is_msg(&self, addr: u64) -> bool {
         let _lock = self.alloc_lock.lock();
 
-        if !self.allocations.contains_key(&addr) {
+        if self.vmas.get(addr).is_none() {
             panic!("PID {}: Address {addr:#X} not allocated", self.id);
         }
 
         self.addr_to_msg_id.contains_key(&addr)
     }
 
-    pub fn allocate(&mut self, len: u64) -> (u64, u64) {
+    /// Changes the access of the area at `addr`, remapping the pages that are mapped. The others
+    /// are mapped as they are accessed.
+    pub fn protect(&mut self, addr: u64, ty: AllocationType) {
         let _lock = self.alloc_lock.lock();
 
-        let page_count = len.div_ceil(PAGE_SIZE);
-        trace!(
-            "PID {}: Allocating {page_count} pages ({len} bytes)",
-            self.id
-        );
-        // TODO: Handle OOM.
-        let addr = unsafe {
-            (*crate::system::state::SYS_STATE.get())
-                .pmm
-                .as_ref()
-                .unwrap()
-                .lock()
-                .alloc(page_count)
-                .addr() as u64
-        };
-        assert_ne!(addr, 0);
-        let virt = addr + skykit::USER_VIRT_OFFSET;
-        drop(_lock);
-        self.track_alloc(virt, len, AllocationType::Writable);
-        (virt, page_count)
+        let vma = self.vmas.get_mut(addr).unwrap();
+        let old = core::mem::replace(&mut vma.ty, ty);
+        trace!("PID {}: Protecting {addr:#X} ({old:?} -> {ty:?})", self.id);
+
+        let mut cr3 = self.cr3.lock();
+        for i in 0..vma.page_count() {
+            let Some(phys) = vma.frame(i) else {
+                continue;
+            };
+            let virt = addr + i * PAGE_SIZE;
+            if !old.is_accessible() || unsafe { cr3.virt_to_phys(virt) }.is_none() {
+                continue;
+            }
+            unsafe {
+                if ty.is_accessible() {
+                    cr3.remap(virt, phys, Self::page_flags(ty, vma.copy_on_write(phys)));
+                } else {
+                    cr3.unmap(virt, 1);
+                }
+            }
+        }
+    }
+
+    /// Reserves a stack with a guard area below it, returning the bottom of the stack.
+    pub fn allocate_stack(&mut self) -> Result<u64, OutOfMemory> {
+        let guard = self
+            .vmas
+            .find_free(STACK_GUARD_LEN + STACK_LEN)
+            .ok_or(OutOfMemory)?;
+        let addr = guard + STACK_GUARD_LEN;
+        self.track_alloc(
+            guard,
+            VirtualArea::new_on_demand(STACK_GUARD_LEN, AllocationType::Guard),
+        )?;
+        self.track_alloc(
+            addr,
+            VirtualArea::new_on_demand(STACK_LEN, AllocationType::Writable),
+        )?;
+        self.stacks.insert(addr);
+        Ok(addr)
+    }
+
+    /// Reserves zeroed memory anywhere in the address space, backed as it is accessed.
+    pub fn allocate(&mut self, len: u64) -> Result<u64, AllocError> {
+        trace!("PID {}: Allocating {len} bytes", self.id);
+        self.check_limit(len)?;
+        Ok(self.map_anywhere(VirtualArea::new_on_demand(len, AllocationType::Writable))?)
     }
 }
 
 impl Drop for Process {
     fn drop(&mut self) {
-        let addrs: Vec<_> = self.allocations.keys().copied().collect();
+        let addrs: Vec<_> = self.vmas.addrs().collect();
         for addr in addrs {
             self.free_alloc(addr);
         }
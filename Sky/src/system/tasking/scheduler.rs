@@ -1,18 +1,21 @@
 // Copyright (c) ChefKiss 2021-2025. Licensed under the Thou Shalt Not Profit License version 1.5. See LICENSE for details.
 
 use alloc::{string::String, vec::Vec};
-use core::{cell::SyncUnsafeCell, ops::ControlFlow};
+use core::{cell::SyncUnsafeCell, ops::ControlFlow, sync::atomic::Ordering};
 
+use amd64::paging::PAGE_SIZE;
 use hashbrown::HashMap;
 use skykit::{
     msg::{KernelMessage, Message},
+    syscall::{args, SystemCallError},
     TerminationReason,
 };
 
 use crate::{
     system::{
         gdt::{PrivilegeLevel, SegmentSelector},
-        tasking::AllocationType,
+        osdt::{LiveSource, ROOT_ID},
+        tasking::{userland::SystemCallResult, vma::VirtualArea, AllocError},
         tss::TaskSegmentSelector,
         RegisterState,
     },
@@ -32,6 +35,10 @@ pub struct Scheduler {
     pub pid_gen: crate::incr_id::IncrementalIDGen,
     pub tid_gen: crate::incr_id::IncrementalIDGen,
     pub msg_id_gen: crate::incr_id::IncrementalIDGen,
+    pub lapic_ticks_per_ms: u32,
+    pub tsc_ticks_per_ms: u64,
+    pub last_switch_tsc: u64,
+    pub dt_entry: u64,
 }
 
 unsafe extern "sysv64" fn irq_handler(state: &mut RegisterState) {
@@ -43,20 +50,32 @@ unsafe extern "sysv64" fn irq_handler(state: &mut RegisterState) {
         .unwrap()
         .lock();
     let pid = this.irq_handlers.get(&irq).copied().unwrap();
-    let s: &mut [u8] = postcard::to_allocvec(&KernelMessage::IRQFired(irq))
-        .unwrap()
-        .leak();
+    let data = postcard::to_allocvec(&KernelMessage::IRQFired(irq)).unwrap();
 
-    let virt = this
+    let virt = match this
         .processes
         .get_mut(&pid)
         .unwrap()
-        .track_kernelside_alloc(s.as_ptr() as _, s.len() as _);
+        .track_kernelside_alloc(&data)
+    {
+        Ok(v) => v,
+        Err(e) => {
+            warn!("Dropping IRQ {irq} for PID {pid}: {e:?}");
+            crate::acpi::ioapic::set_irq_mask(irq, false);
+            let current_pid = this.current_pid;
+            if e == AllocError::OutOfMemory
+                && this.out_of_memory().is_some_and(|v| Some(v) == current_pid)
+            {
+                this.schedule(state);
+            }
+            return;
+        }
+    };
 
     let msg = Message::new(
         this.msg_id_gen.next(),
         0,
-        core::slice::from_raw_parts(virt as *const _, s.len() as _),
+        core::slice::from_raw_parts(virt as *const _, data.len()),
     );
     this.message_sources.insert(msg.id, 0);
     let process = this.processes.get_mut(&pid).unwrap();
@@ -68,6 +87,16 @@ unsafe extern "sysv64" fn irq_handler(state: &mut RegisterState) {
     }
 }
 
+/// Loads the kernel's page tables, so that those of the current process can be freed.
+unsafe fn load_kernel_cr3() {
+    (*crate::system::state::SYS_STATE.get())
+        .pml4
+        .as_ref()
+        .unwrap()
+        .lock()
+        .set_cr3();
+}
+
 extern "C" fn idle() {
     crate::hlt_loop!();
 }
@@ -88,8 +117,9 @@ impl Scheduler {
 
         unsafe {
             let gdt = &mut *crate::system::gdt::GDT.get();
-            (*TSS.get()) =
-                TaskSegmentSelector::new(kern_stack.as_ptr() as u64 + kern_stack.len() as u64);
+            let kern_rsp = kern_stack.as_ptr() as u64 + kern_stack.len() as u64;
+            (*TSS.get()) = TaskSegmentSelector::new(kern_rsp);
+            (*crate::system::cpu_local::BSP.get()).kernel_rsp = kern_rsp;
             let tss_addr = TSS.get() as u64;
             gdt.task_segment.base_low = tss_addr as u16;
             gdt.task_segment.base_middle = (tss_addr >> 16) as u8;
@@ -105,7 +135,11 @@ impl Scheduler {
         }
 
         let state = unsafe { &mut *crate::system::state::SYS_STATE.get() };
-        state.lapic.as_ref().unwrap().setup_timer(timer);
+        let lapic_ticks_per_ms = state.lapic.as_ref().unwrap().setup_timer(timer);
+
+        let tsc = unsafe { core::arch::x86_64::_rdtsc() };
+        timer.sleep(10);
+        let tsc_ticks_per_ms = (unsafe { core::arch::x86_64::_rdtsc() } - tsc) / 10;
 
         crate::interrupts::idt::set_handler(128, 1, PrivilegeLevel::Supervisor, schedule, true);
         crate::acpi::ioapic::wire_legacy_irq(96, false);
@@ -121,6 +155,10 @@ impl Scheduler {
             pid_gen: crate::incr_id::IncrementalIDGen::new(),
             tid_gen: crate::incr_id::IncrementalIDGen::new(),
             msg_id_gen: crate::incr_id::IncrementalIDGen::new(),
+            lapic_ticks_per_ms,
+            tsc_ticks_per_ms,
+            last_switch_tsc: 0,
+            dt_entry: crate::system::osdt::new_entry(ROOT_ID, "Processes", HashMap::new(), None),
         }
     }
 
@@ -132,61 +170,67 @@ impl Scheduler {
         unsafe { core::arch::asm!("int 128", options(nostack, preserves_flags)) }
     }
 
-    pub fn spawn_proc(&mut self, path: String, exec_data: &[u8]) -> &mut super::Thread {
-        let exec = elf::ElfBytes::<elf::endian::NativeEndian>::minimal_parse(exec_data).unwrap();
-        assert_eq!(exec.ehdr.e_type, elf::abi::ET_DYN);
-        assert_eq!(exec.ehdr.class, elf::file::Class::ELF64);
-        assert_ne!(exec.ehdr.e_entry, 0);
-
-        let segments = exec.segments().unwrap();
+    pub fn spawn_proc(
+        &mut self,
+        path: String,
+        exec_data: &[u8],
+        libraries: &HashMap<String, Vec<u8>>,
+        memory_limit: Option<u64>,
+    ) -> Result<&mut super::Thread, super::image::SpawnError> {
+        let image = super::image::Image::load(exec_data, libraries)?;
 
-        let max_vaddr = segments
-            .iter()
-            .map(|v| v.p_vaddr + v.p_memsz)
-            .max()
-            .unwrap();
-        let data = vec![0; max_vaddr as usize].leak();
-        for hdr in segments.iter().filter(|v| v.p_type == elf::abi::PT_LOAD) {
-            let fsz = hdr.p_filesz as usize;
-            let foff = hdr.p_offset as usize;
-            let ext_vaddr = hdr.p_vaddr as usize;
-            data[ext_vaddr..ext_vaddr + fsz].copy_from_slice(&exec_data[foff..foff + fsz]);
+        let pid = self.pid_gen.next();
+        let mut properties = HashMap::from([
+            ("PID".into(), pid.into()),
+            ("Path".into(), path.as_str().into()),
+        ]);
+        if let Some(v) = memory_limit {
+            properties.insert("MemoryLimit".into(), v.into());
         }
-
-        let virt_addr =
-            data.as_ptr() as u64 - amd64::paging::PHYS_VIRT_OFFSET + skykit::USER_VIRT_OFFSET;
-        for v in exec.section_headers().unwrap().iter() {
-            let Ok(relas) = exec.section_data_as_relas(&v) else {
-                continue;
-            };
-            for reloc in relas {
-                let ptr = unsafe { &mut *data.as_mut_ptr().add(reloc.r_offset as _).cast::<u64>() };
-                match reloc.r_type {
-                    elf::abi::R_X86_64_NONE => {}
-                    elf::abi::R_X86_64_RELATIVE => {
-                        *ptr = virt_addr.checked_add_signed(reloc.r_addend).unwrap()
-                    }
-                    v => unimplemented!("{v:#X}"),
-                }
+        let proc_entry = crate::system::osdt::new_entry(
+            self.dt_entry,
+            path.rsplit('.').next().unwrap(),
+            properties,
+            Some(LiveSource::Process(pid)),
+        );
+        let Ok(proc) = self.processes.try_insert(
+            pid,
+            super::Process::new(pid, path, image.objects[0].base, proc_entry, memory_limit),
+        ) else {
+            unreachable!()
+        };
+        // Every run is tracked even if mapping one fails, so that they are freed with the process.
+        let mut mapped = unsafe { proc.cr3.lock().map_higher_half() };
+        for object in &image.objects {
+            let mut offset = 0;
+            for run in object.page_types.chunk_by(|a, b| a == b) {
+                let len = run.len() as u64 * PAGE_SIZE;
+                let tracked = proc.track_alloc(
+                    object.base + offset,
+                    VirtualArea::new(len, object.phys + offset, run[0]),
+                );
+                mapped = mapped.and(tracked);
+                offset += len;
             }
         }
-
-        let pid = self.pid_gen.next();
-        let Ok(proc) = self
-            .processes
-            .try_insert(pid, super::Process::new(pid, path, virt_addr))
-        else {
-            unreachable!()
+        let Ok(stack_addr) = mapped.and_then(|()| proc.allocate_stack()) else {
+            let proc = self.processes.remove(&pid).unwrap();
+            crate::system::osdt::remove_entry(proc.dt_entry);
+            self.pid_gen.free(pid);
+            return Err(super::image::SpawnError::OutOfMemory);
         };
-        unsafe { proc.cr3.lock().map_higher_half() }
-        proc.track_alloc(virt_addr, data.len() as _, AllocationType::Writable);
         let tid = self.tid_gen.next();
-        let stack_addr = proc.allocate(super::STACK_LEN).0;
-        let thread = proc.new_thread(tid, virt_addr + exec.ehdr.e_entry, stack_addr);
+        let thread_entry = crate::system::osdt::new_entry(
+            proc_entry,
+            "Thread",
+            HashMap::from([("TID".into(), tid.into())]),
+            Some(LiveSource::Thread(tid)),
+        );
+        let thread = proc.new_thread(tid, image.entry, stack_addr, thread_entry);
         let Ok(thread) = self.threads.try_insert(tid, thread) else {
             unreachable!()
         };
-        thread
+        Ok(thread)
     }
 
     pub fn current_thread_mut(&mut self) -> Option<&mut super::Thread> {
@@ -214,7 +258,12 @@ impl Scheduler {
     }
 
     pub unsafe fn schedule(&mut self, state: &mut RegisterState) {
+        let now = core::arch::x86_64::_rdtsc();
+        let elapsed = now - self.last_switch_tsc;
+        self.last_switch_tsc = now;
+
         if let Some(old_thread) = self.current_thread_mut() {
+            old_thread.cpu_time += elapsed;
             old_thread.regs = *state;
             if !old_thread.state.is_suspended() {
                 old_thread.state = super::ThreadState::Inactive;
@@ -222,6 +271,10 @@ impl Scheduler {
         }
 
         let Some(thread) = self.next_thread_mut() else {
+            let sys_state = &mut *crate::system::state::SYS_STATE.get();
+            if core::mem::take(&mut sys_state.snapshot_on_idle) {
+                crate::system::osdt::dump_snapshot(self);
+            }
             *state = RegisterState {
                 rip: idle as usize as _,
                 cs: SegmentSelector::new(1, PrivilegeLevel::Supervisor).into(),
@@ -230,12 +283,7 @@ impl Scheduler {
                 ss: SegmentSelector::new(2, PrivilegeLevel::Supervisor).into(),
                 ..Default::default()
             };
-            (*crate::system::state::SYS_STATE.get())
-                .pml4
-                .as_ref()
-                .unwrap()
-                .lock()
-                .set_cr3();
+            load_kernel_cr3();
             self.current_tid = None;
             self.current_pid = None;
             return;
@@ -250,17 +298,16 @@ impl Scheduler {
         self.current_pid = Some(pid);
     }
 
-    pub fn register_irq(
-        &mut self,
-        state: &RegisterState,
-    ) -> ControlFlow<Option<TerminationReason>> {
-        let irq = state.rsi as u8;
+    pub fn register_irq(&mut self, args: args::RegisterIRQ) -> SystemCallResult {
+        let Ok(irq) = u8::try_from(args.irq) else {
+            return Err(SystemCallError::MalformedArgument);
+        };
         if irq > 0xDF {
-            return ControlFlow::Break(Some(TerminationReason::MalformedArgument));
+            return Err(SystemCallError::MalformedArgument);
         }
         let pid = self.current_pid.unwrap();
         if self.irq_handlers.try_insert(irq, pid).is_err() {
-            return ControlFlow::Break(Some(TerminationReason::AlreadyExists));
+            return Err(SystemCallError::AlreadyExists);
         }
 
         crate::acpi::ioapic::wire_legacy_irq(irq, false);
@@ -272,19 +319,23 @@ impl Scheduler {
             true,
         );
 
-        ControlFlow::Continue(())
+        Ok(ControlFlow::Continue(()))
     }
 
     pub fn thread_teardown(&mut self) -> ControlFlow<Option<TerminationReason>> {
         let id = self.current_tid.take().unwrap();
-        self.threads.remove(&id);
+        let thread = self.threads.remove(&id).unwrap();
+        crate::system::osdt::remove_entry(thread.dt_entry);
         self.tid_gen.free(id);
 
         let proc = self.current_process_mut().unwrap();
         proc.thread_ids.remove(&id);
         if proc.thread_ids.is_empty() {
             let pid = self.current_pid.take().unwrap();
-            self.processes.remove(&pid);
+            unsafe { load_kernel_cr3() }
+            let proc = self.processes.remove(&pid).unwrap();
+            crate::system::osdt::remove_entry(proc.dt_entry);
+            crate::system::fkext::detach_process(pid);
             self.pid_gen.free(pid);
         }
 
@@ -292,14 +343,53 @@ impl Scheduler {
     }
 
     pub fn process_teardown(&mut self) {
+        let pid = self.current_pid.unwrap();
+        self.kill(pid);
+    }
+
+    pub fn kill(&mut self, pid: u64) {
         // TODO: Teardown any residual messages too.
-        self.current_tid = None;
-        let pid = self.current_pid.take().unwrap();
+        if self.current_pid == Some(pid) {
+            self.current_tid = None;
+            self.current_pid = None;
+            unsafe { load_kernel_cr3() }
+        }
         let proc = self.processes.remove(&pid).unwrap();
         for tid in &proc.thread_ids {
             self.threads.remove(tid);
             self.tid_gen.free(*tid);
         }
+        self.irq_handlers.retain(|&irq, v| {
+            if *v == pid {
+                crate::acpi::ioapic::set_irq_mask(irq, true);
+            }
+            *v != pid
+        });
+        crate::system::osdt::remove_entry(proc.dt_entry);
+        crate::system::fkext::detach_process(pid);
         self.pid_gen.free(pid);
     }
+
+    /// Applies the out-of-memory policy after an allocation failed, returning the PID of the
+    /// process that was killed to free memory, if any.
+    ///
+    /// The caller has to reschedule if it is the current process.
+    pub fn out_of_memory(&mut self) -> Option<u64> {
+        let state = unsafe { &*crate::system::state::SYS_STATE.get() };
+        if !state.oom_kill {
+            return None;
+        }
+        let (pid, pages) = self
+            .processes
+            .iter()
+            .map(|(&pid, proc)| (pid, proc.resident_pages()))
+            .max_by_key(|&(_, pages)| pages)?;
+        warn!(
+            "Out of memory, killing PID {pid} ({}) to free {pages} pages",
+            self.processes[&pid].path
+        );
+        state.oom_kills.fetch_add(1, Ordering::Relaxed);
+        self.kill(pid);
+        Some(pid)
+    }
 }
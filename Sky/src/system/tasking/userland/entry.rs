@@ -0,0 +1,130 @@
+// Copyright (c) ChefKiss 2021-2025. Licensed under the Thou Shalt Not Profit License version 1.5. See LICENSE for details.
+
+use core::mem::offset_of;
+
+use amd64::msr::{
+    efer::ExtendedFeatureEnableReg,
+    syscall::{LongModeSystemCallTargetAddrReg, SystemCallFlagMaskReg, SystemCallTargetAddrReg},
+    ModelSpecificReg,
+};
+
+use crate::system::{
+    cpu_local::{KERNEL_RSP_OFF, USER_RSP_OFF},
+    gdt::{PrivilegeLevel, SegmentSelector},
+    RegisterState,
+};
+
+// TF, IF, DF, IOPL, NT and AC.
+const SYSCALL_FLAG_MASK: u32 = 0x4_7700;
+const USER_CS: u16 = SegmentSelector::new(4, PrivilegeLevel::User).0;
+const USER_SS: u16 = SegmentSelector::new(3, PrivilegeLevel::User).0;
+
+// Builds the same frame as the `int 249` path so both share `syscall_handler` and the scheduler.
+// The frame is left with SYSRET only if that restores it exactly, otherwise with IRETQ.
+#[unsafe(naked)]
+unsafe extern "sysv64" fn syscall_entry() {
+    core::arch::naked_asm!(
+        "swapgs",
+        "mov gs:[{user_rsp}], rsp",
+        "mov rsp, gs:[{kernel_rsp}]",
+        "and rsp, -16",
+        "push {user_ss}",
+        "push qword ptr gs:[{user_rsp}]",
+        "swapgs",
+        "push r11",
+        "push {user_cs}",
+        "push rcx",
+        "push 0",
+        "push 249",
+        "push rax",
+        "push rbx",
+        "push rcx",
+        "push rdx",
+        "push rsi",
+        "push rdi",
+        "push rbp",
+        "push r8",
+        "push r9",
+        "push r10",
+        "push r11",
+        "push r12",
+        "push r13",
+        "push r14",
+        "push r15",
+        "mov rdi, rsp",
+        "call {handler}",
+        "mov rax, [rsp + {rip}]",
+        "cmp rax, [rsp + {rcx}]",
+        "jne 2f",
+        "shr rax, 47",
+        "jnz 2f",
+        "mov rax, [rsp + {rflags}]",
+        "cmp rax, [rsp + {r11}]",
+        "jne 2f",
+        "cmp qword ptr [rsp + {cs}], {user_cs}",
+        "jne 2f",
+        "pop r15",
+        "pop r14",
+        "pop r13",
+        "pop r12",
+        "pop r11",
+        "pop r10",
+        "pop r9",
+        "pop r8",
+        "pop rbp",
+        "pop rdi",
+        "pop rsi",
+        "pop rdx",
+        "pop rcx",
+        "pop rbx",
+        "pop rax",
+        "mov rsp, [rsp + {user_rsp_frame}]",
+        "sysretq",
+        "2:",
+        "pop r15",
+        "pop r14",
+        "pop r13",
+        "pop r12",
+        "pop r11",
+        "pop r10",
+        "pop r9",
+        "pop r8",
+        "pop rbp",
+        "pop rdi",
+        "pop rsi",
+        "pop rdx",
+        "pop rcx",
+        "pop rbx",
+        "pop rax",
+        "add rsp, 16",
+        "iretq",
+        user_rsp = const USER_RSP_OFF,
+        kernel_rsp = const KERNEL_RSP_OFF,
+        user_ss = const USER_SS,
+        user_cs = const USER_CS,
+        handler = sym super::syscall_handler,
+        rip = const offset_of!(RegisterState, rip),
+        rcx = const offset_of!(RegisterState, rcx),
+        rflags = const offset_of!(RegisterState, rflags),
+        r11 = const offset_of!(RegisterState, r11),
+        cs = const offset_of!(RegisterState, cs),
+        user_rsp_frame = const offset_of!(RegisterState, rsp) - offset_of!(RegisterState, int_num),
+    );
+}
+
+pub unsafe fn setup() {
+    crate::system::cpu_local::install();
+    ExtendedFeatureEnableReg::read()
+        .with_syscall_ext(true)
+        .write();
+    SystemCallTargetAddrReg::new()
+        .with_syscall_cs_ss(SegmentSelector::new(1, PrivilegeLevel::Supervisor).0)
+        .with_sysret_cs_ss(SegmentSelector::new(2, PrivilegeLevel::User).0)
+        .write();
+    LongModeSystemCallTargetAddrReg::new()
+        .with_target(syscall_entry as usize as u64)
+        .write();
+    SystemCallFlagMaskReg::new()
+        .with_mask(SYSCALL_FLAG_MASK)
+        .write();
+}
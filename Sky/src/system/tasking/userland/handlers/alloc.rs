@@ -3,41 +3,130 @@
 use core::ops::ControlFlow;
 
 use amd64::paging::PAGE_SIZE;
-use skykit::TerminationReason;
+use skykit::{
+    syscall::{args, rets, MapFlags, SystemCallError},
+    TerminationReason,
+};
 
-use crate::system::{tasking::scheduler::Scheduler, RegisterState};
+use crate::system::tasking::{
+    scheduler::Scheduler,
+    userland::SystemCallResult,
+    vma::{Backing, VirtualArea},
+    AllocationType, Process,
+};
+
+pub fn alloc(scheduler: &mut Scheduler, args: args::Allocate) -> SystemCallResult<rets::Allocate> {
+    if args.size == 0 {
+        return Err(SystemCallError::MalformedArgument);
+    }
+    let addr = scheduler
+        .current_process_mut()
+        .unwrap()
+        .allocate(args.size)?;
+    Ok(ControlFlow::Continue(rets::Allocate { addr }))
+}
+
+pub fn free(scheduler: &mut Scheduler, args: args::Free) -> SystemCallResult {
+    let addr = args.addr;
 
-pub fn alloc(
-    scheduler: &mut Scheduler,
-    state: &mut RegisterState,
-) -> ControlFlow<Option<TerminationReason>> {
     let process = scheduler.current_process_mut().unwrap();
-    let (addr, pages) = process.allocate(state.rsi);
+    if process.vmas.get(addr).is_none() {
+        return Err(SystemCallError::MalformedArgument);
+    }
+    if process.is_msg(addr) {
+        return Ok(ControlFlow::Break(Some(
+            TerminationReason::MalformedAddress,
+        )));
+    }
 
-    unsafe {
-        core::ptr::write_bytes(addr as *mut u8, 0, (pages * PAGE_SIZE) as _);
+    if process.region_is_mapped(addr, args.size) {
+        process.free_alloc(addr);
+        Ok(ControlFlow::Continue(()))
+    } else {
+        Err(SystemCallError::MalformedArgument)
+    }
+}
+
+const fn access(flags: MapFlags) -> Result<AllocationType, SystemCallError> {
+    if !MapFlags::ALL.contains(flags) {
+        return Err(SystemCallError::MalformedArgument);
+    }
+    match (
+        flags.contains(MapFlags::WRITE),
+        flags.contains(MapFlags::EXECUTE),
+    ) {
+        (true, true) => Err(SystemCallError::InsufficientPermissions),
+        (true, false) => Ok(AllocationType::Writable),
+        (false, true) => Ok(AllocationType::Executable),
+        (false, false) if flags.contains(MapFlags::READ) => Ok(AllocationType::Readable),
+        (false, false) => Ok(AllocationType::Guard),
     }
+}
 
-    state.rax = addr;
-    ControlFlow::Continue(())
+/// Makes `addr..addr + len` an area of its own if it lies within one the process may change.
+fn isolate_user_range(process: &mut Process, addr: u64, len: u64) -> Result<(), SystemCallError> {
+    if len == 0 || !addr.is_multiple_of(PAGE_SIZE) {
+        return Err(SystemCallError::MalformedArgument);
+    }
+    let Some((start, vma)) = process.vmas.containing_pages(addr, len) else {
+        return Err(SystemCallError::MalformedArgument);
+    };
+    if vma.ty == AllocationType::Kernel
+        || matches!(vma.backing, Backing::Shared(_))
+        || process.addr_to_msg_id.contains_key(&start)
+    {
+        return Err(SystemCallError::InsufficientPermissions);
+    }
+    process.vmas.isolate(addr, len);
+    Ok(())
 }
 
-pub fn free(
-    scheduler: &mut Scheduler,
-    state: &RegisterState,
-) -> ControlFlow<Option<TerminationReason>> {
-    let addr = state.rsi;
+pub fn map(scheduler: &mut Scheduler, args: args::Map) -> SystemCallResult<rets::Map> {
+    let flags = MapFlags(args.flags);
+    let ty = access(flags)?;
+    if args.len == 0 {
+        return Err(SystemCallError::MalformedArgument);
+    }
 
     let process = scheduler.current_process_mut().unwrap();
-    if process.is_msg(addr) {
-        return ControlFlow::Break(Some(TerminationReason::MalformedAddress));
+    let addr = if flags.contains(MapFlags::FIXED) {
+        if !args.addr.is_multiple_of(PAGE_SIZE) {
+            return Err(SystemCallError::MalformedArgument);
+        }
+        if !process.vmas.is_free(args.addr, args.len) {
+            return Err(SystemCallError::AlreadyExists);
+        }
+        args.addr
+    } else {
+        let Some(addr) = process.vmas.find_free(args.len) else {
+            return Err(SystemCallError::OutOfMemory);
+        };
+        addr
+    };
+    if ty != AllocationType::Guard {
+        process.check_limit(args.len)?;
     }
+    process.track_alloc(addr, VirtualArea::new_on_demand(args.len, ty))?;
 
-    let size = state.rdx;
-    if process.region_is_mapped(addr, size) {
-        process.free_alloc(state.rsi);
-        ControlFlow::Continue(())
-    } else {
-        ControlFlow::Break(Some(TerminationReason::MalformedArgument))
+    Ok(ControlFlow::Continue(rets::Map { addr }))
+}
+
+pub fn unmap(scheduler: &mut Scheduler, args: args::Unmap) -> SystemCallResult {
+    let process = scheduler.current_process_mut().unwrap();
+    isolate_user_range(process, args.addr, args.len)?;
+    process.free_alloc(args.addr);
+    Ok(ControlFlow::Continue(()))
+}
+
+pub fn protect(scheduler: &mut Scheduler, args: args::Protect) -> SystemCallResult {
+    let flags = MapFlags(args.flags);
+    if flags.contains(MapFlags::FIXED) {
+        return Err(SystemCallError::MalformedArgument);
     }
+    let ty = access(flags)?;
+
+    let process = scheduler.current_process_mut().unwrap();
+    isolate_user_range(process, args.addr, args.len)?;
+    process.protect(args.addr, ty);
+    Ok(ControlFlow::Continue(()))
 }
@@ -2,33 +2,31 @@
 
 use core::{fmt::Write, ops::ControlFlow};
 
-use skykit::TerminationReason;
+use skykit::{
+    syscall::{args, SystemCallError},
+    TerminationReason,
+};
 
-use crate::system::{tasking::scheduler::Scheduler, RegisterState};
+use crate::system::tasking::{
+    scheduler::Scheduler,
+    userland::{user_ptr::UserSlice, SystemCallResult},
+};
 
 pub mod alloc;
 pub mod msg;
 pub mod os_dt_entry;
 pub mod port;
 
-pub fn kprint(
-    scheduler: &Scheduler,
-    state: &RegisterState,
-) -> ControlFlow<Option<TerminationReason>> {
-    let addr = state.rsi;
-    let size = state.rdx;
-
-    if !scheduler
-        .current_process()
-        .unwrap()
-        .region_is_valid(addr, size)
-    {
-        return ControlFlow::Break(Some(TerminationReason::MalformedAddress));
-    }
-
-    let s = unsafe { core::slice::from_raw_parts(addr as *const _, size as _) };
-    let Ok(s) = core::str::from_utf8(s) else {
-        return ControlFlow::Break(Some(TerminationReason::MalformedBody));
+pub fn kprint(scheduler: &Scheduler, args: args::KPrint) -> SystemCallResult {
+    let Some(s) = UserSlice::<u8>::new(scheduler.current_process().unwrap(), args.ptr, args.len)?
+    else {
+        return Ok(ControlFlow::Break(Some(
+            TerminationReason::MalformedAddress,
+        )));
+    };
+    let s = s.copy_in();
+    let Ok(s) = core::str::from_utf8(&s) else {
+        return Err(SystemCallError::MalformedBody);
     };
 
     write!(crate::system::serial::SERIAL.lock(), "{s}").unwrap();
@@ -37,5 +35,5 @@ pub fn kprint(
         write!(v, "{s}").unwrap();
     }
 
-    ControlFlow::Continue(())
+    Ok(ControlFlow::Continue(()))
 }
@@ -2,18 +2,29 @@
 
 use core::ops::ControlFlow;
 
-use amd64::paging::{PageTableFlags, PAGE_SIZE};
 use hashbrown::HashSet;
 use skykit::{
     msg::{KernelMessage, Message},
+    syscall::{args, rets, SystemCallError, SystemCallRegisters},
     TerminationReason,
 };
 
-use crate::system::{
-    tasking::{scheduler::Scheduler, ThreadState},
-    RegisterState,
+use crate::system::tasking::{
+    scheduler::Scheduler,
+    userland::{user_ptr::UserSlice, SystemCallResult},
+    vma::{self, Backing, VirtualArea},
+    AllocationType, ThreadState,
 };
 
+fn recv_results(msg: &Message) -> rets::MsgRecv {
+    rets::MsgRecv {
+        id: msg.id,
+        pid: msg.pid,
+        ptr: msg.data.as_ptr() as u64,
+        len: msg.data.len() as u64,
+    }
+}
+
 pub fn handle_new(
     scheduler: &mut Scheduler,
     pid: u64,
@@ -27,10 +38,10 @@ pub fn handle_new(
             continue;
         }
         thread.state = ThreadState::Inactive;
-        thread.regs.rax = msg.id;
-        thread.regs.rdi = msg.pid;
-        thread.regs.rsi = msg.data.as_ptr() as _;
-        thread.regs.rdx = msg.data.len() as _;
+        thread.regs.rax = 0;
+        thread
+            .regs
+            .set_syscall_results(recv_results(&msg).into_registers());
         if idle {
             return ControlFlow::Break(None);
         }
@@ -41,98 +52,123 @@ pub fn handle_new(
     ControlFlow::Continue(())
 }
 
-pub fn send(
-    scheduler: &mut Scheduler,
-    state: &RegisterState,
-) -> ControlFlow<Option<TerminationReason>> {
+pub fn send(scheduler: &mut Scheduler, args: args::MsgSend) -> SystemCallResult {
     let src = scheduler.current_pid.unwrap();
-    let target = state.rsi;
-    if src == target {
-        return ControlFlow::Break(Some(TerminationReason::MalformedArgument));
+    let target = args.pid;
+    if src == target || args.len == 0 {
+        return Err(SystemCallError::MalformedArgument);
     }
 
-    let (addr, size) = (state.rdx, state.rcx);
-    if !scheduler
-        .current_process()
-        .unwrap()
-        .region_is_within_bounds(addr, size)
+    let process = scheduler.current_process().unwrap();
+    let Some(data) = UserSlice::<u8>::new(process, args.ptr, args.len)?
+        .filter(|v| process.region_is_within_bounds(v.addr(), v.len() as _))
+    else {
+        return Ok(ControlFlow::Break(Some(
+            TerminationReason::MalformedAddress,
+        )));
+    };
+    let (addr, size) = (data.addr(), data.len() as u64);
+    // Only pages backed on demand are copied on write, others would stay writable to the sender,
+    // or could be made so, while the target maps them.
+    if !process
+        .vmas
+        .get(addr)
+        .is_some_and(|v| matches!(v.backing, Backing::OnDemand(_)))
     {
-        return ControlFlow::Break(Some(TerminationReason::MalformedAddress));
+        return Err(SystemCallError::InsufficientPermissions);
     }
 
     if !scheduler.processes.contains_key(&target) {
-        return ControlFlow::Break(Some(TerminationReason::NotFound));
+        return Err(SystemCallError::NotFound);
     }
+    let frames = scheduler.current_process().unwrap().share(addr, size);
 
-    let msg = Message::new(scheduler.msg_id_gen.next(), src, unsafe {
-        core::slice::from_raw_parts(addr as *const _, size as _)
-    });
-    scheduler.message_sources.insert(msg.id, src);
+    let process = scheduler.processes.get_mut(&target).unwrap();
+    let Some(target_addr) = process.vmas.find_free(size) else {
+        for phys in frames {
+            vma::unshare_frame(phys);
+        }
+        return Err(SystemCallError::OutOfMemory);
+    };
+    if let Err(e) = process.track_alloc(
+        target_addr,
+        VirtualArea::new_shared(size, frames, AllocationType::Readable),
+    ) {
+        process.free_alloc(target_addr);
+        return Err(e.into());
+    }
 
-    let cur = scheduler.current_process_mut().unwrap();
+    let msg_id = scheduler.msg_id_gen.next();
+    scheduler
+        .processes
+        .get_mut(&target)
+        .unwrap()
+        .msg_mappings
+        .insert(msg_id, target_addr);
+    scheduler
+        .current_process_mut()
+        .unwrap()
+        .track_msg(msg_id, addr);
 
-    cur.track_msg(msg.id, addr);
+    // The buffer is shared with the target rather than copied, the kernel never reads it.
+    // Its pages are copy-on-write for the sender while the target maps them.
+    let msg = Message::new(msg_id, src, unsafe {
+        core::slice::from_raw_parts(target_addr as *const _, size as _)
+    });
+    scheduler.message_sources.insert(msg.id, src);
 
-    let process = scheduler.processes.get(&target).unwrap();
-    unsafe {
-        process.cr3.lock().map(
-            addr,
-            addr - skykit::USER_VIRT_OFFSET,
-            size.div_ceil(PAGE_SIZE),
-            PageTableFlags::new_present().with_user(true),
-        );
-    }
-    let tids = process.thread_ids.clone();
-    handle_new(scheduler, target, tids, msg)
+    let tids = scheduler.processes[&target].thread_ids.clone();
+    Ok(handle_new(scheduler, target, tids, msg))
 }
 
-pub fn recv(
-    scheduler: &mut Scheduler,
-    state: &mut RegisterState,
-) -> ControlFlow<Option<TerminationReason>> {
+pub fn recv(scheduler: &mut Scheduler) -> SystemCallResult<rets::MsgRecv> {
     let process = scheduler.current_process_mut().unwrap();
     let Some(msg) = process.messages.pop_back() else {
         scheduler.current_thread_mut().unwrap().state = ThreadState::Suspended;
-        return ControlFlow::Break(None);
+        return Ok(ControlFlow::Break(None));
     };
 
-    state.rax = msg.id;
-    state.rdi = msg.pid;
-    state.rsi = msg.data.as_ptr() as u64;
-    state.rdx = msg.data.len() as u64;
-    ControlFlow::Continue(())
+    Ok(ControlFlow::Continue(recv_results(&msg)))
 }
 
-pub fn ack(
-    scheduler: &mut Scheduler,
-    state: &RegisterState,
-) -> ControlFlow<Option<TerminationReason>> {
-    let msg_id = state.rsi;
+pub fn ack(scheduler: &mut Scheduler, args: args::MsgAck) -> SystemCallResult {
+    let msg_id = args.id;
 
-    let Some(src_pid) = scheduler.message_sources.remove(&msg_id) else {
-        return ControlFlow::Break(Some(TerminationReason::NotFound));
+    let Some(&src_pid) = scheduler.message_sources.get(&msg_id) else {
+        return Err(SystemCallError::NotFound);
     };
 
     let cur_pid = scheduler.current_pid.unwrap();
     let pid = if src_pid == 0 { cur_pid } else { src_pid };
+    if pid != cur_pid
+        && !scheduler
+            .current_process()
+            .unwrap()
+            .msg_mappings
+            .contains_key(&msg_id)
+    {
+        return Err(SystemCallError::NotFound);
+    }
+    scheduler.message_sources.remove(&msg_id);
+
     let process = scheduler.processes.get_mut(&pid).unwrap();
     let addr = *process.msg_id_to_addr.get(&msg_id).unwrap();
-    let size = process.allocations.get(&addr).copied().unwrap().0;
+    let size = process.vmas.get(addr).unwrap().len;
     if src_pid == 0 {
-        let msg: KernelMessage = unsafe {
-            postcard::from_bytes(core::slice::from_raw_parts(addr as *const _, size as _)).unwrap()
-        };
+        let data = UserSlice::<u8>::new(process, addr, size)?
+            .unwrap()
+            .copy_in();
+        let msg: KernelMessage = postcard::from_bytes(&data).unwrap();
         let KernelMessage::IRQFired(irq) = msg;
         crate::acpi::ioapic::set_irq_mask(irq, false);
     }
     process.free_msg(msg_id);
     scheduler.msg_id_gen.free(msg_id);
     if pid != cur_pid {
-        let process = scheduler.current_process().unwrap();
-        unsafe {
-            process.cr3.lock().unmap(addr, size.div_ceil(PAGE_SIZE));
-        }
+        let process = scheduler.current_process_mut().unwrap();
+        let addr = process.msg_mappings.remove(&msg_id).unwrap();
+        process.free_alloc(addr);
     }
 
-    ControlFlow::Continue(())
+    Ok(ControlFlow::Continue(()))
 }
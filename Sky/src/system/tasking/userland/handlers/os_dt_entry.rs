@@ -1,107 +1,165 @@
 // Copyright (c) ChefKiss 2021-2025. Licensed under the Thou Shalt Not Profit License version 1.5. See LICENSE for details.
 
+use alloc::string::String;
 use core::ops::ControlFlow;
 
+use hashbrown::HashMap;
 use skykit::{
-    osdtentry::{OSDTEntryInfo, OSDTEntryProp},
+    osdtentry::{is_reserved_key, OSDTEntryInfo, OSDTEntryProp, OSDTENTRY_NAME_KEY},
+    syscall::{args, rets, SystemCallError},
     TerminationReason,
 };
 
-use crate::system::{tasking::scheduler::Scheduler, RegisterState};
+use crate::system::tasking::{
+    scheduler::Scheduler,
+    userland::{user_ptr::UserSlice, SystemCallResult},
+};
+
+pub fn new_entry(
+    scheduler: &Scheduler,
+    args: args::NewOSDTEntry,
+) -> SystemCallResult<rets::NewOSDTEntry> {
+    let pid = scheduler.current_pid.unwrap();
+
+    let mut properties = HashMap::new();
+    if args.name_ptr != 0 {
+        let Some(name) = UserSlice::<u8>::new(
+            scheduler.current_process().unwrap(),
+            args.name_ptr,
+            args.name_len,
+        )?
+        else {
+            return Ok(ControlFlow::Break(Some(
+                TerminationReason::MalformedAddress,
+            )));
+        };
+        let Ok(name) = String::from_utf8(name.copy_in()) else {
+            return Err(SystemCallError::MalformedBody);
+        };
+        properties.insert(OSDTENTRY_NAME_KEY.into(), name.into());
+    }
 
-pub fn new_entry(state: &mut RegisterState) -> ControlFlow<Option<TerminationReason>> {
     let sys_state = unsafe { &mut *crate::system::state::SYS_STATE.get() };
     let dt_index = sys_state.dt_index.as_ref().unwrap();
     let new = {
         let dt_index = dt_index.read();
-        let Some(parent) = dt_index.get(&state.rsi) else {
-            return ControlFlow::Break(Some(TerminationReason::NotFound));
+        let Some(parent) = dt_index.get(&args.parent) else {
+            return Err(SystemCallError::NotFound);
         };
+        let mut parent = parent.lock();
+        if !parent.is_modifiable_by(pid) {
+            return Err(SystemCallError::InsufficientPermissions);
+        }
         let v = crate::system::state::OSDTEntry {
             id: sys_state.dt_id_gen.as_ref().unwrap().lock().next(),
-            parent: Some(state.rsi.into()),
+            parent: Some(args.parent.into()),
+            properties,
+            owner_pid: Some(pid),
             ..Default::default()
         };
-        parent.lock().children.push(v.id.into());
+        parent.children.push(v.id.into());
         v
     };
-    state.rax = new.id;
-    dt_index.write().insert(new.id, new.into());
+    let id = new.id;
+    dt_index.write().insert(id, new.into());
 
-    ControlFlow::Continue(())
+    Ok(ControlFlow::Continue(rets::NewOSDTEntry { id }))
 }
 
 pub fn get_info(
     scheduler: &mut Scheduler,
-    state: &mut RegisterState,
-) -> ControlFlow<Option<TerminationReason>> {
+    args: args::GetOSDTEntryInfo,
+) -> SystemCallResult<rets::GetOSDTEntryInfo> {
     let sys_state = unsafe { &mut *crate::system::state::SYS_STATE.get() };
-    let Ok(info_type) = OSDTEntryInfo::try_from(state.rdx) else {
-        return ControlFlow::Break(Some(TerminationReason::MalformedArgument));
+    let Ok(info_type) = OSDTEntryInfo::try_from(args.ty) else {
+        return Err(SystemCallError::MalformedArgument);
+    };
+    let key = if info_type == OSDTEntryInfo::Property {
+        if args.key_ptr == 0 || args.key_len == 0 {
+            return Err(SystemCallError::MalformedArgument);
+        }
+        let Some(key) = UserSlice::<u8>::new(
+            scheduler.current_process().unwrap(),
+            args.key_ptr,
+            args.key_len,
+        )?
+        else {
+            return Ok(ControlFlow::Break(Some(
+                TerminationReason::MalformedAddress,
+            )));
+        };
+        let Ok(key) = String::from_utf8(key.copy_in()) else {
+            return Err(SystemCallError::MalformedBody);
+        };
+        Some(key)
+    } else {
+        None
     };
     let dt_index = sys_state.dt_index.as_ref().unwrap().read();
-    let Some(ent) = dt_index.get(&state.rsi) else {
-        return ControlFlow::Break(Some(TerminationReason::NotFound));
+    let Some(ent) = dt_index.get(&args.id) else {
+        return Err(SystemCallError::NotFound);
     };
     let data = {
-        let ent = ent.lock();
+        let mut ent = ent.lock();
+        crate::system::osdt::refresh(&mut ent, scheduler);
         match info_type {
             OSDTEntryInfo::Parent => postcard::to_allocvec(&ent.parent),
             OSDTEntryInfo::Children => postcard::to_allocvec(&ent.children),
             OSDTEntryInfo::Properties => postcard::to_allocvec(&ent.properties),
             OSDTEntryInfo::Property => {
-                let data = state.rcx as *const u8;
-                let len = state.r8 as usize;
-                if data.is_null() || len == 0 {
-                    return ControlFlow::Break(Some(TerminationReason::MalformedArgument));
-                }
-                let Ok(k) = core::str::from_utf8(unsafe { core::slice::from_raw_parts(data, len) })
-                else {
-                    return ControlFlow::Break(Some(TerminationReason::MalformedBody));
-                };
-                postcard::to_allocvec(&ent.properties.get(k))
+                postcard::to_allocvec(&ent.properties.get(key.as_deref().unwrap()))
             }
         }
         .unwrap()
-        .leak()
     };
 
-    state.rax = scheduler
+    let ptr = scheduler
         .current_process_mut()
         .unwrap()
-        .track_kernelside_alloc(data.as_ptr() as _, data.len() as _);
-    state.rdi = data.len() as _;
+        .track_kernelside_alloc(&data)?;
 
-    ControlFlow::Continue(())
+    Ok(ControlFlow::Continue(rets::GetOSDTEntryInfo {
+        ptr,
+        len: data.len() as _,
+    }))
 }
 
-pub fn set_prop(
-    scheduler: &mut Scheduler,
-    state: &RegisterState,
-) -> ControlFlow<Option<TerminationReason>> {
-    let addr = state.rdx;
-    let len = state.rcx;
-
-    if !scheduler
-        .current_process()
-        .unwrap()
-        .region_is_valid(addr, len)
-    {
-        return ControlFlow::Break(Some(TerminationReason::MalformedAddress));
+pub fn set_prop(scheduler: &mut Scheduler, args: args::SetOSDTEntryProp) -> SystemCallResult {
+    let Some(data) =
+        UserSlice::<u8>::new(scheduler.current_process().unwrap(), args.ptr, args.len)?
+    else {
+        return Ok(ControlFlow::Break(Some(
+            TerminationReason::MalformedAddress,
+        )));
+    };
+    let Ok(v) = postcard::from_bytes::<OSDTEntryProp>(&data.copy_in()) else {
+        return Err(SystemCallError::MalformedBody);
+    };
+    if is_reserved_key(&v.0) {
+        return Err(SystemCallError::InsufficientPermissions);
+    }
+    if !v.1.is_finite() {
+        return Err(SystemCallError::MalformedArgument);
     }
 
     let sys_state = unsafe { &mut *crate::system::state::SYS_STATE.get() };
     let dt_index = sys_state.dt_index.as_ref().unwrap().read();
-    let Some(ent) = dt_index.get(&state.rsi) else {
-        return ControlFlow::Break(Some(TerminationReason::NotFound));
-    };
-    let data = unsafe { core::slice::from_raw_parts(addr as *const _, len as _) };
-    let Ok(v) = postcard::from_bytes::<OSDTEntryProp>(data) else {
-        return ControlFlow::Break(Some(TerminationReason::MalformedAddress));
+    let Some(ent) = dt_index.get(&args.id) else {
+        return Err(SystemCallError::NotFound);
     };
-    ent.lock().properties.insert(v.0, v.1);
+    let mut ent = ent.lock();
+    if !ent.is_modifiable_by(scheduler.current_pid.unwrap()) {
+        return Err(SystemCallError::InsufficientPermissions);
+    }
+    ent.properties.insert(v.0, v.1);
+    drop(ent);
     drop(dt_index);
-    crate::system::fkext::handle_change(scheduler, state.rsi.into());
+    crate::system::fkext::handle_change(scheduler, args.id.into());
+
+    Ok(ControlFlow::Continue(()))
+}
 
-    ControlFlow::Continue(())
+pub fn snapshot(scheduler: &Scheduler) -> SystemCallResult {
+    crate::system::osdt::dump_snapshot(scheduler);
+    Ok(ControlFlow::Continue(()))
 }
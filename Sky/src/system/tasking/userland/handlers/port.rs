@@ -3,36 +3,36 @@
 use core::ops::ControlFlow;
 
 use amd64::io::port::PortIO;
-use skykit::{syscall::AccessSize, TerminationReason};
+use skykit::syscall::{args, rets, AccessSize, SystemCallError};
 
-use crate::system::RegisterState;
+use crate::system::tasking::userland::SystemCallResult;
 
-pub fn port_in(state: &mut RegisterState) -> ControlFlow<Option<TerminationReason>> {
-    let port = state.rsi as u16;
-    let Ok(access_size) = AccessSize::try_from(state.rdx) else {
-        return ControlFlow::Break(Some(TerminationReason::MalformedArgument));
+pub fn port_in(args: args::PortIn) -> SystemCallResult<rets::PortIn> {
+    let port = args.port as u16;
+    let Ok(access_size) = AccessSize::try_from(args.size) else {
+        return Err(SystemCallError::MalformedArgument);
     };
-    unsafe {
-        state.rax = match access_size {
+    let value = unsafe {
+        match access_size {
             AccessSize::Byte => u64::from(u8::read(port)),
             AccessSize::Word => u64::from(u16::read(port)),
             AccessSize::DWord => u64::from(u32::read(port)),
-        };
-    }
-    ControlFlow::Continue(())
+        }
+    };
+    Ok(ControlFlow::Continue(rets::PortIn { value }))
 }
 
-pub fn port_out(state: &RegisterState) -> ControlFlow<Option<TerminationReason>> {
-    let port = state.rsi as u16;
-    let Ok(access_size) = AccessSize::try_from(state.rdx) else {
-        return ControlFlow::Break(Some(TerminationReason::MalformedArgument));
+pub fn port_out(args: args::PortOut) -> SystemCallResult {
+    let port = args.port as u16;
+    let Ok(access_size) = AccessSize::try_from(args.size) else {
+        return Err(SystemCallError::MalformedArgument);
     };
     unsafe {
         match access_size {
-            AccessSize::Byte => u8::write(port, state.rcx as u8),
-            AccessSize::Word => u16::write(port, state.rcx as u16),
-            AccessSize::DWord => u32::write(port, state.rcx as u32),
+            AccessSize::Byte => u8::write(port, args.value as u8),
+            AccessSize::Word => u16::write(port, args.value as u16),
+            AccessSize::DWord => u32::write(port, args.value as u32),
         };
     }
-    ControlFlow::Continue(())
+    Ok(ControlFlow::Continue(()))
 }
@@ -2,37 +2,113 @@
 
 use core::ops::ControlFlow;
 
-use skykit::{syscall::SystemCall, TerminationReason};
+use skykit::{
+    syscall::{SystemCall, SystemCallError, SystemCallRegisters},
+    TerminationReason,
+};
 
-use crate::system::{gdt::PrivilegeLevel, RegisterState};
+use crate::system::{gdt::PrivilegeLevel, pmm::OutOfMemory, tasking::AllocError, RegisterState};
 
+mod entry;
 pub mod handlers;
 pub mod page_table;
+pub mod user_ptr;
+
+pub type SystemCallResult<T = ()> =
+    Result<ControlFlow<Option<TerminationReason>, T>, SystemCallError>;
+
+impl From<OutOfMemory> for SystemCallError {
+    fn from(_: OutOfMemory) -> Self {
+        Self::OutOfMemory
+    }
+}
+
+impl From<AllocError> for SystemCallError {
+    fn from(value: AllocError) -> Self {
+        match value {
+            AllocError::OutOfMemory => Self::OutOfMemory,
+            AllocError::LimitExceeded => Self::LimitExceeded,
+        }
+    }
+}
+
+impl RegisterState {
+    const fn syscall_args(&self) -> [u64; 4] {
+        [self.rsi, self.rdx, self.r10, self.r8]
+    }
+
+    pub const fn set_syscall_results(&mut self, regs: [u64; 4]) {
+        [self.rdi, self.rsi, self.rdx, self.r10] = regs;
+    }
+}
+
+fn dispatch<A: SystemCallRegisters, R: SystemCallRegisters>(
+    state: &mut RegisterState,
+    handler: impl FnOnce(A) -> SystemCallResult<R>,
+) -> SystemCallResult {
+    Ok(match handler(A::from_registers(state.syscall_args()))? {
+        ControlFlow::Continue(v) => {
+            state.set_syscall_results(v.into_registers());
+            ControlFlow::Continue(())
+        }
+        ControlFlow::Break(v) => ControlFlow::Break(v),
+    })
+}
 
 unsafe extern "sysv64" fn syscall_handler(state: &mut RegisterState) {
     let sys_state = &mut *crate::system::state::SYS_STATE.get();
     let mut scheduler = sys_state.scheduler.as_ref().unwrap().lock();
+    let scheduler = &mut *scheduler;
 
     let flow = SystemCall::try_from(state.rdi).map_or(
-        ControlFlow::Break(Some(TerminationReason::MalformedArgument)),
+        Err(SystemCallError::UnknownSystemCall),
         |v| match v {
-            SystemCall::KPrint => handlers::kprint(&scheduler, state),
-            SystemCall::MsgRecv => handlers::msg::recv(&mut scheduler, state),
-            SystemCall::MsgSend => handlers::msg::send(&mut scheduler, state),
-            SystemCall::Quit => scheduler.thread_teardown(),
-            SystemCall::Yield => ControlFlow::Break(None),
-            SystemCall::PortIn => handlers::port::port_in(state),
-            SystemCall::PortOut => handlers::port::port_out(state),
-            SystemCall::RegisterIRQ => scheduler.register_irq(state),
-            SystemCall::Allocate => handlers::alloc::alloc(&mut scheduler, state),
-            SystemCall::Free => handlers::alloc::free(&mut scheduler, state),
-            SystemCall::MsgAck => handlers::msg::ack(&mut scheduler, state),
-            SystemCall::NewOSDTEntry => handlers::os_dt_entry::new_entry(state),
-            SystemCall::GetOSDTEntryInfo => handlers::os_dt_entry::get_info(&mut scheduler, state),
-            SystemCall::SetOSDTEntryProp => handlers::os_dt_entry::set_prop(&mut scheduler, state),
+            SystemCall::KPrint => dispatch(state, |a| handlers::kprint(scheduler, a)),
+            SystemCall::MsgRecv => dispatch(state, |()| handlers::msg::recv(scheduler)),
+            SystemCall::MsgSend => dispatch(state, |a| handlers::msg::send(scheduler, a)),
+            SystemCall::Quit => Ok(scheduler.thread_teardown()),
+            SystemCall::Yield => Ok(ControlFlow::Break(None)),
+            SystemCall::PortIn => dispatch(state, handlers::port::port_in),
+            SystemCall::PortOut => dispatch(state, handlers::port::port_out),
+            SystemCall::RegisterIRQ => dispatch(state, |a| scheduler.register_irq(a)),
+            SystemCall::Allocate => dispatch(state, |a| handlers::alloc::alloc(scheduler, a)),
+            SystemCall::Free => dispatch(state, |a| handlers::alloc::free(scheduler, a)),
+            SystemCall::MsgAck => dispatch(state, |a| handlers::msg::ack(scheduler, a)),
+            SystemCall::NewOSDTEntry => {
+                dispatch(state, |a| handlers::os_dt_entry::new_entry(scheduler, a))
+            }
+            SystemCall::GetOSDTEntryInfo => {
+                dispatch(state, |a| handlers::os_dt_entry::get_info(scheduler, a))
+            }
+            SystemCall::SetOSDTEntryProp => {
+                dispatch(state, |a| handlers::os_dt_entry::set_prop(scheduler, a))
+            }
+            SystemCall::OSDTSnapshot => handlers::os_dt_entry::snapshot(scheduler),
+            SystemCall::Map => dispatch(state, |a| handlers::alloc::map(scheduler, a)),
+            SystemCall::Unmap => dispatch(state, |a| handlers::alloc::unmap(scheduler, a)),
+            SystemCall::Protect => dispatch(state, |a| handlers::alloc::protect(scheduler, a)),
         },
     );
 
+    let flow = match flow {
+        Ok(v) => {
+            state.rax = 0;
+            v
+        }
+        Err(e) => {
+            // The caller may be the one that has to go to free up memory.
+            let pid = scheduler.current_pid;
+            if e == SystemCallError::OutOfMemory
+                && scheduler.out_of_memory().is_some_and(|v| Some(v) == pid)
+            {
+                scheduler.schedule(state);
+            } else {
+                state.rax = e.into();
+            }
+            return;
+        }
+    };
+
     let ControlFlow::Break(reason) = flow else {
         return;
     };
@@ -49,4 +125,5 @@ unsafe extern "sysv64" fn syscall_handler(state: &mut RegisterState) {
 
 pub fn setup() {
     crate::interrupts::idt::set_handler(249, 1, PrivilegeLevel::User, syscall_handler, false);
+    unsafe { entry::setup() }
 }
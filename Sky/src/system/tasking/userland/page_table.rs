@@ -1,34 +1,58 @@
 // Copyright (c) ChefKiss 2021-2025. Licensed under the Thou Shalt Not Profit License version 1.5. See LICENSE for details.
 
-use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::{cell::RefCell, convert::Infallible, sync::atomic::Ordering};
 
-use amd64::paging::{PageTable, PageTableFlags, PAGE_SIZE};
+use amd64::paging::{PageTable, PageTableFlags, TlbFlush};
 
-use crate::system::tasking::AllocationType;
+use crate::system::{
+    hardening::NX_ACTIVE,
+    pmm::{self, OutOfMemory},
+};
 
+/// Address space of a process, freeing the page table frames it allocated when dropped.
 #[derive(Debug)]
 #[repr(C)]
-pub struct UserPML4(PageTable<{ amd64::paging::PHYS_VIRT_OFFSET }>, u64);
+pub struct UserPML4(
+    PageTable<{ amd64::paging::PHYS_VIRT_OFFSET }>,
+    RefCell<Vec<u64>>,
+);
 
 impl UserPML4 {
     #[inline]
-    pub const fn new(pid: u64) -> Self {
-        Self(amd64::paging::PageTable::new(), pid)
+    pub const fn new() -> Self {
+        Self(amd64::paging::PageTable::new(), RefCell::new(Vec::new()))
     }
 
-    fn alloc_entry(pid: u64) -> u64 {
-        let sys_state = unsafe { &mut *crate::system::state::SYS_STATE.get() };
-        let phys = Box::leak(Box::new(PageTable::<0>::new())) as *mut _ as u64
-            - amd64::paging::PHYS_VIRT_OFFSET;
+    fn alloc_entry(frames: &RefCell<Vec<u64>>) -> Result<u64, OutOfMemory> {
+        let mut frames = frames.borrow_mut();
+        frames.try_reserve(1).map_err(|_| OutOfMemory)?;
+        let phys = pmm::alloc_zeroed(1)?;
+        frames.push(phys);
+        Ok(phys)
+    }
+
+    fn free_entry(frames: &RefCell<Vec<u64>>, phys: u64) {
+        let mut frames = frames.borrow_mut();
+        let i = frames.iter().position(|&v| v == phys).unwrap();
+        frames.swap_remove(i);
+        unsafe { pmm::free(phys, 1) }
+    }
 
-        let scheduler = sys_state.scheduler.as_mut().unwrap().get_mut();
-        scheduler.processes.get_mut(&pid).unwrap().track_alloc(
-            phys + skykit::USER_VIRT_OFFSET,
-            PAGE_SIZE,
-            AllocationType::Kernel,
+    /// User pages, images included, are never both writable and executable, though without NX
+    /// every page is executable.
+    fn user_flags(flags: PageTableFlags) -> PageTableFlags {
+        assert!(
+            !(flags.writable && flags.executable),
+            "Mapping a user page writable and executable"
         );
+        flags.with_executable(flags.executable || !NX_ACTIVE.load(Ordering::Relaxed))
+    }
 
-        phys
+    /// Frames used by the tables, the top level one included.
+    #[inline]
+    pub fn frame_count(&self) -> u64 {
+        self.1.borrow().len() as u64 + 1
     }
 
     #[inline]
@@ -36,11 +60,42 @@ impl UserPML4 {
         self.0.set_cr3();
     }
 
+    /// Maps `count` pages, failing if there is no memory left for the tables. The pages mapped
+    /// before the failure stay mapped.
     #[inline]
-    pub unsafe fn map(&mut self, virt: u64, phys: u64, count: u64, flags: PageTableFlags) {
-        let pid = self.1;
-        self.0
-            .map(&move || Self::alloc_entry(pid), virt, phys, count, flags);
+    pub unsafe fn map(
+        &mut self,
+        virt: u64,
+        phys: u64,
+        count: u64,
+        flags: PageTableFlags,
+    ) -> Result<(), OutOfMemory> {
+        let frames = &self.1;
+        self.0.map(
+            &|| Self::alloc_entry(frames),
+            virt,
+            phys,
+            count,
+            Self::user_flags(flags),
+        )
+    }
+
+    /// Changes the frame or the flags of a mapped page, which needs no new tables.
+    #[inline]
+    pub unsafe fn remap(&mut self, virt: u64, phys: u64, flags: PageTableFlags) {
+        self.0.unmap(virt, 1);
+        let Ok(()) = self.0.map(
+            &|| -> Result<u64, Infallible> { unreachable!("Unmapping a page keeps its tables") },
+            virt,
+            phys,
+            1,
+            Self::user_flags(flags),
+        );
+    }
+
+    #[inline]
+    pub unsafe fn virt_to_phys(&mut self, virt: u64) -> Option<(u64, PageTableFlags)> {
+        self.0.virt_to_phys(virt)
     }
 
     #[inline]
@@ -48,9 +103,33 @@ impl UserPML4 {
         self.0.unmap(virt, count);
     }
 
+    /// Unmaps whatever is mapped within the range, freeing the tables left empty.
+    #[inline]
+    pub unsafe fn unmap_range(&mut self, virt: u64, count: u64) {
+        let frames = &self.1;
+        let mut flush = TlbFlush::new();
+        self.0
+            .unmap_range(
+                virt,
+                count,
+                &|phys| Self::free_entry(frames, phys),
+                &mut flush,
+            )
+            .unwrap();
+        flush.flush();
+    }
+
     #[inline]
-    pub unsafe fn map_higher_half(&mut self) {
-        let pid = self.1;
-        self.0.map_higher_half(&move || Self::alloc_entry(pid));
+    pub unsafe fn map_higher_half(&mut self) -> Result<(), OutOfMemory> {
+        let frames = &self.1;
+        self.0.map_higher_half(&|| Self::alloc_entry(frames))
+    }
+}
+
+impl Drop for UserPML4 {
+    fn drop(&mut self) {
+        for &phys in self.1.get_mut().iter() {
+            unsafe { pmm::free(phys, 1) }
+        }
     }
 }
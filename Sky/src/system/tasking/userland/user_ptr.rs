@@ -0,0 +1,71 @@
+// Copyright (c) ChefKiss 2021-2025. Licensed under the Thou Shalt Not Profit License version 1.5. See LICENSE for details.
+
+use alloc::vec::Vec;
+use core::{marker::PhantomData, sync::atomic::Ordering};
+
+use crate::system::{hardening::SMAP_ACTIVE, pmm::OutOfMemory, tasking::Process};
+
+fn with_user_access<R>(f: impl FnOnce() -> R) -> R {
+    let smap = SMAP_ACTIVE.load(Ordering::Relaxed);
+    if smap {
+        unsafe { core::arch::asm!("stac", options(nostack)) }
+    }
+    let ret = f();
+    if smap {
+        unsafe { core::arch::asm!("clac", options(nostack)) }
+    }
+    ret
+}
+
+/// A user buffer checked against the allocations of the process that passed it.
+///
+/// Pages of the buffer that were never accessed are backed on creation, as the kernel cannot
+/// take page faults itself, which fails if memory ran out.
+#[derive(Debug, Clone, Copy)]
+pub struct UserSlice<T: Copy> {
+    addr: u64,
+    len: usize,
+    __: PhantomData<*const T>,
+}
+
+impl<T: Copy> UserSlice<T> {
+    pub fn new(process: &Process, addr: u64, len: u64) -> Result<Option<Self>, OutOfMemory> {
+        if !addr.is_multiple_of(core::mem::align_of::<T>() as u64) {
+            return Ok(None);
+        }
+        let Some((size, len)) = len
+            .checked_mul(core::mem::size_of::<T>() as u64)
+            .zip(len.try_into().ok())
+        else {
+            return Ok(None);
+        };
+        if process.user_allocation(addr, size).is_none() {
+            return Ok(None);
+        }
+        process.populate(addr, size)?;
+        Ok(Some(Self {
+            addr,
+            len,
+            __: PhantomData,
+        }))
+    }
+
+    #[inline]
+    pub const fn addr(&self) -> u64 {
+        self.addr
+    }
+
+    #[inline]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn copy_in(&self) -> Vec<T> {
+        let mut ret = Vec::with_capacity(self.len);
+        with_user_access(|| unsafe {
+            core::ptr::copy_nonoverlapping(self.addr as *const T, ret.as_mut_ptr(), self.len);
+            ret.set_len(self.len);
+        });
+        ret
+    }
+}
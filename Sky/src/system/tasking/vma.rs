@@ -0,0 +1,257 @@
+// Copyright (c) ChefKiss 2021-2025. Licensed under the Thou Shalt Not Profit License version 1.5. See LICENSE for details.
+
+use alloc::{collections::BTreeMap, vec::Vec};
+
+use amd64::paging::PAGE_SIZE;
+
+use super::AllocationType;
+
+/// Lowest address handed out to userspace, keeping the first pages unmapped to catch null pointers.
+pub const USER_BASE: u64 = 0x20_0000;
+/// End of the lower canonical half, minus a guard page.
+pub const USER_END: u64 = 0x7FFF_FFFF_F000;
+
+#[derive(Debug)]
+pub enum Backing {
+    /// Frames starting at the given physical address.
+    Contiguous(u64),
+    /// Single frames allocated on first access, keyed by page index.
+    OnDemand(spin::Mutex<BTreeMap<u64, u64>>),
+    /// Frames of another process' area, each holding a reference.
+    Shared(Vec<u64>),
+}
+
+/// Takes another reference to `phys`.
+pub fn share_frame(phys: u64) {
+    let refs = unsafe { &(*crate::system::state::SYS_STATE.get()).frame_refs };
+    *refs.lock().entry(phys).or_insert(1) += 1;
+}
+
+/// Drops a reference to `phys`, returning whether it was the last one and the frame must be freed.
+pub fn unshare_frame(phys: u64) -> bool {
+    let refs = unsafe { &(*crate::system::state::SYS_STATE.get()).frame_refs };
+    let mut refs = refs.lock();
+    let Some(count) = refs.get_mut(&phys) else {
+        return true;
+    };
+    *count -= 1;
+    if *count == 1 {
+        refs.remove(&phys);
+    }
+    false
+}
+
+pub fn is_shared(phys: u64) -> bool {
+    let refs = unsafe { &(*crate::system::state::SYS_STATE.get()).frame_refs };
+    refs.lock().contains_key(&phys)
+}
+
+/// A contiguous range of a process' address space.
+#[derive(Debug)]
+pub struct VirtualArea {
+    pub len: u64,
+    pub ty: AllocationType,
+    pub backing: Backing,
+}
+
+impl VirtualArea {
+    #[inline]
+    pub const fn new(len: u64, phys: u64, ty: AllocationType) -> Self {
+        Self {
+            len,
+            ty,
+            backing: Backing::Contiguous(phys),
+        }
+    }
+
+    #[inline]
+    pub const fn new_on_demand(len: u64, ty: AllocationType) -> Self {
+        Self {
+            len,
+            ty,
+            backing: Backing::OnDemand(spin::Mutex::new(BTreeMap::new())),
+        }
+    }
+
+    #[inline]
+    pub const fn new_shared(len: u64, frames: Vec<u64>, ty: AllocationType) -> Self {
+        Self {
+            len,
+            ty,
+            backing: Backing::Shared(frames),
+        }
+    }
+
+    #[inline]
+    pub const fn page_count(&self) -> u64 {
+        self.len.div_ceil(PAGE_SIZE)
+    }
+
+    #[inline]
+    pub const fn end(&self, addr: u64) -> u64 {
+        addr + self.page_count() * PAGE_SIZE
+    }
+
+    /// Splits the area at `offset`, a multiple of the page size, returning the upper part.
+    pub fn split_off(&mut self, offset: u64) -> Self {
+        let index = (offset / PAGE_SIZE) as usize;
+        let backing = match &mut self.backing {
+            Backing::Contiguous(phys) => Backing::Contiguous(*phys + offset),
+            Backing::OnDemand(frames) => Backing::OnDemand(spin::Mutex::new(
+                frames
+                    .get_mut()
+                    .split_off(&(index as u64))
+                    .into_iter()
+                    .map(|(k, v)| (k - index as u64, v))
+                    .collect(),
+            )),
+            Backing::Shared(frames) => Backing::Shared(frames.split_off(index)),
+        };
+        let upper = Self {
+            len: self.len - offset,
+            ty: self.ty,
+            backing,
+        };
+        self.len = offset;
+        upper
+    }
+
+    /// Whether writes to `phys` must be preceded by a copy, only done for on-demand areas as
+    /// messages are only sent from those.
+    pub fn copy_on_write(&self, phys: u64) -> bool {
+        self.ty == AllocationType::Writable
+            && matches!(self.backing, Backing::OnDemand(_))
+            && is_shared(phys)
+    }
+
+    /// Pages backed by a frame.
+    pub fn resident_pages(&self) -> u64 {
+        match &self.backing {
+            Backing::Contiguous(_) | Backing::Shared(_) => self.page_count(),
+            Backing::OnDemand(frames) => frames.lock().len() as u64,
+        }
+    }
+
+    /// The frame backing the page at `index`, if it has one yet.
+    pub fn frame(&self, index: u64) -> Option<u64> {
+        match &self.backing {
+            Backing::Contiguous(phys) => Some(phys + index * PAGE_SIZE),
+            Backing::OnDemand(frames) => frames.lock().get(&index).copied(),
+            Backing::Shared(frames) => Some(frames[index as usize]),
+        }
+    }
+}
+
+/// The areas of a process' address space, keyed by their page-aligned start.
+#[derive(Debug, Default)]
+pub struct AddressSpace(BTreeMap<u64, VirtualArea>);
+
+impl AddressSpace {
+    #[inline]
+    pub const fn new() -> Self {
+        Self(BTreeMap::new())
+    }
+
+    /// First fit search for `len` bytes of free address space.
+    pub fn find_free(&self, len: u64) -> Option<u64> {
+        let len = len.checked_next_multiple_of(PAGE_SIZE)?.max(PAGE_SIZE);
+        let mut cursor = USER_BASE;
+        for (&addr, vma) in &self.0 {
+            if addr.saturating_sub(cursor) >= len {
+                return Some(cursor);
+            }
+            cursor = cursor.max(vma.end(addr));
+        }
+        (USER_END.saturating_sub(cursor) >= len).then_some(cursor)
+    }
+
+    pub fn is_free(&self, addr: u64, len: u64) -> bool {
+        let Some(end) = len
+            .checked_next_multiple_of(PAGE_SIZE)
+            .and_then(|v| addr.checked_add(v))
+        else {
+            return false;
+        };
+        addr >= USER_BASE
+            && end <= USER_END
+            && self
+                .0
+                .range(..end)
+                .next_back()
+                .is_none_or(|(&k, v)| v.end(k) <= addr)
+    }
+
+    /// Returns `false` without inserting if the area is empty or overlaps another.
+    pub fn insert(&mut self, addr: u64, vma: VirtualArea) -> bool {
+        if vma.len == 0 || !addr.is_multiple_of(PAGE_SIZE) || !self.is_free(addr, vma.len) {
+            return false;
+        }
+        self.0.insert(addr, vma);
+        true
+    }
+
+    #[inline]
+    pub fn get(&self, addr: u64) -> Option<&VirtualArea> {
+        self.0.get(&addr)
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, addr: u64) -> Option<&mut VirtualArea> {
+        self.0.get_mut(&addr)
+    }
+
+    /// The area containing the pages of `addr..addr + len`, if they all belong to the same one.
+    pub fn containing_pages(&self, addr: u64, len: u64) -> Option<(u64, &VirtualArea)> {
+        let end = addr.checked_add(len.checked_next_multiple_of(PAGE_SIZE)?)?;
+        self.0
+            .range(..=addr)
+            .next_back()
+            .filter(|(&k, v)| end <= v.end(k))
+            .map(|(&k, v)| (k, v))
+    }
+
+    /// Splits the area containing `addr..addr + len` so that the range becomes an area of its own.
+    ///
+    /// `addr` must be page-aligned and the range must be within a single area.
+    pub fn isolate(&mut self, addr: u64, len: u64) -> Option<&mut VirtualArea> {
+        let (start, _) = self.containing_pages(addr, len)?;
+        let len = len.next_multiple_of(PAGE_SIZE);
+        let mut vma = self.0.remove(&start).unwrap();
+        if addr > start {
+            let upper = vma.split_off(addr - start);
+            self.0.insert(start, vma);
+            vma = upper;
+        }
+        if len < vma.len {
+            let rest = vma.split_off(len);
+            self.0.insert(addr + len, rest);
+        }
+        self.0.insert(addr, vma);
+        self.0.get_mut(&addr)
+    }
+
+    #[inline]
+    pub fn remove(&mut self, addr: u64) -> Option<VirtualArea> {
+        self.0.remove(&addr)
+    }
+
+    /// The area `addr..addr + len` lies entirely within, if any.
+    pub fn containing(&self, addr: u64, len: u64) -> Option<(u64, &VirtualArea)> {
+        let end = addr.checked_add(len)?;
+        self.0
+            .range(..=addr)
+            .next_back()
+            .filter(|(&k, v)| end <= k + v.len)
+            .map(|(&k, v)| (k, v))
+    }
+
+    #[inline]
+    pub fn areas(&self) -> impl Iterator<Item = (u64, &VirtualArea)> + '_ {
+        self.0.iter().map(|(&k, v)| (k, v))
+    }
+
+    #[inline]
+    pub fn addrs(&self) -> impl Iterator<Item = u64> + '_ {
+        self.0.keys().copied()
+    }
+}
@@ -36,10 +36,10 @@ impl Terminal {
         unsafe {
             let state = &mut *super::state::SYS_STATE.get();
             let base = self.fb.base as u64;
-            state.pml4.as_ref().unwrap().lock().map(
+            state.pml4.as_ref().unwrap().lock().map_range(
                 base,
                 base - amd64::paging::PHYS_VIRT_OFFSET,
-                (self.fb.height * self.fb.stride).div_ceil(PAGE_SIZE as usize) as _,
+                ((self.fb.height * self.fb.stride) as u64).next_multiple_of(PAGE_SIZE),
                 PageTableFlags::new_present()
                     .with_writable(true)
                     .with_pat_entry(2),
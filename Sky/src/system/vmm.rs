@@ -1,13 +1,14 @@
 // Copyright (c) ChefKiss 2021-2025. Licensed under the Thou Shalt Not Profit License version 1.5. See LICENSE for details.
 
 use alloc::boxed::Box;
+use core::convert::Infallible;
 
 use amd64::{
     msr::{
         pat::{PATEntry, PageAttributeTable},
         ModelSpecificReg,
     },
-    paging::{PageTable, PageTableFlags},
+    paging::{PageSize, PageTable, PageTableFlags},
 };
 
 #[repr(transparent)]
@@ -25,9 +26,9 @@ impl PageTableLvl4 {
         Self(amd64::paging::PageTable::new())
     }
 
-    fn alloc_entry() -> u64 {
-        Box::leak(Box::new(PageTable::<0>::new())) as *mut _ as u64
-            - amd64::paging::PHYS_VIRT_OFFSET
+    fn alloc_entry() -> Result<u64, Infallible> {
+        Ok(Box::leak(Box::new(PageTable::<0>::new())) as *mut _ as u64
+            - amd64::paging::PHYS_VIRT_OFFSET)
     }
 
     pub unsafe fn set_cr3(&mut self) {
@@ -35,11 +36,23 @@ impl PageTableLvl4 {
     }
 
     pub unsafe fn map(&mut self, virt: u64, phys: u64, count: u64, flags: PageTableFlags) {
-        self.0.map(&Self::alloc_entry, virt, phys, count, flags);
+        let Ok(()) = self.0.map(&Self::alloc_entry, virt, phys, count, flags);
+    }
+
+    /// Maps `len` bytes with the largest pages the alignment of `virt` and `phys` allows.
+    pub unsafe fn map_range(&mut self, virt: u64, phys: u64, len: u64, flags: PageTableFlags) {
+        let Ok(()) = self.0.map_range(
+            &Self::alloc_entry,
+            virt,
+            phys,
+            len,
+            flags,
+            PageSize::largest_supported(),
+        );
     }
 
     pub unsafe fn map_higher_half(&mut self) {
-        self.0.map_higher_half(&Self::alloc_entry);
+        let Ok(()) = self.0.map_higher_half(&Self::alloc_entry);
     }
 
     pub unsafe fn init(&mut self) {
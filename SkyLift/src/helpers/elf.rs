@@ -52,7 +52,7 @@ pub fn parse(mem_mgr: &mut super::mem::MemoryManager, buffer: &[u8]) -> skyliftk
         .as_ptr() as u64,
         lowest_addr_phys,
     );
-    mem_mgr.allocate((lowest_addr_phys, kern_region_pages as _));
+    mem_mgr.allocate((lowest_addr_phys, kern_region_pages as u64 * PAGE_SIZE));
     for phdr in segments
         .iter()
         .filter(|phdr| phdr.p_type == elf::abi::PT_LOAD)
@@ -22,28 +22,46 @@ impl MemoryManager {
         self.entries.push(MemoryData::new(ent.0, ent.1));
     }
 
-    pub fn mem_type_from_desc(&self, desc: &MemoryDescriptor) -> Option<MemoryEntry> {
+    #[inline]
+    pub fn range_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Pushes the entries of `desc`, leaving out the memory the kernel keeps using.
+    ///
+    /// Each allocated range splits at most one descriptor in two, see [`Self::range_count`].
+    pub fn push_entries(&self, desc: &MemoryDescriptor, out: &mut Vec<MemoryEntry>) {
         let data = MemoryData::new(desc.phys_start, desc.page_count * PAGE_SIZE);
 
         match desc.ty {
-            MemoryType::CONVENTIONAL => Some(MemoryEntry::Usable(data)),
+            MemoryType::CONVENTIONAL => out.push(MemoryEntry::Usable(data)),
             MemoryType::LOADER_CODE | MemoryType::LOADER_DATA => {
-                let Some(reserved) = self.entries.iter().find(|v| data.base <= v.base + v.length)
-                else {
-                    return Some(MemoryEntry::BootLoaderReclaimable(data));
-                };
-
-                if data.base + data.length > reserved.base + reserved.length {
-                    return Some(MemoryEntry::BootLoaderReclaimable(MemoryData::new(
-                        data.base + reserved.length,
-                        data.length - reserved.length,
-                    )));
+                let end = data.base + data.length;
+                let mut base = data.base;
+                while base < end {
+                    let Some(reserved) = self
+                        .entries
+                        .iter()
+                        .filter(|v| v.base < end && v.base + v.length > base)
+                        .min_by_key(|v| v.base)
+                    else {
+                        out.push(MemoryEntry::BootLoaderReclaimable(MemoryData::new(
+                            base,
+                            end - base,
+                        )));
+                        break;
+                    };
+                    if reserved.base > base {
+                        out.push(MemoryEntry::BootLoaderReclaimable(MemoryData::new(
+                            base,
+                            reserved.base - base,
+                        )));
+                    }
+                    base = reserved.base + reserved.length;
                 }
-
-                None
             }
-            MemoryType::ACPI_RECLAIM => Some(MemoryEntry::ACPIReclaimable(data)),
-            _ => None,
+            MemoryType::ACPI_RECLAIM => out.push(MemoryEntry::ACPIReclaimable(data)),
+            _ => {}
         }
     }
 }
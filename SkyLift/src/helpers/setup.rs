@@ -5,7 +5,6 @@ use alloc::boxed::Box;
 use uefi::{
     boot::{EventType, TimerTrigger, Tpl},
     proto::console::text::Key,
-    Char16,
 };
 
 pub fn setup() {
@@ -23,64 +22,71 @@ pub fn setup() {
 
     trace!("    2. Modifying paging mappings to map higher-half...");
     unsafe {
-        amd64::paging::PageTable::<0>::from_cr3().map_higher_half(&|| {
-            Box::leak(Box::new(amd64::paging::PageTable::<0>::new())) as *mut _ as u64
+        let Ok(()) = amd64::paging::PageTable::<0>::from_cr3().map_higher_half(&|| {
+            Ok::<_, core::convert::Infallible>(Box::leak(Box::new(
+                amd64::paging::PageTable::<0>::new(),
+            )) as *mut _ as u64)
         });
     }
 }
 
-pub fn check_boot_flags() -> (bool, bool) {
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BootFlags {
+    pub verbose: bool,
+    pub serial_enabled: bool,
+    pub osdt_snapshot: bool,
+    pub oom_kill: bool,
+    pub benchmark: bool,
+}
+
+/// Collects the flags typed before the timeout, any number of them can be combined.
+pub fn check_boot_flags() -> BootFlags {
+    let mut flags = BootFlags::default();
     let timer =
         match unsafe { uefi::boot::create_event(EventType::TIMER, Tpl::CALLBACK, None, None) } {
             Ok(v) => v,
             Err(e) => {
                 warn!("Failed to create timer: {e}.");
-                return (false, false);
+                return flags;
             }
         };
     if let Err(e) = uefi::boot::set_timer(&timer, TimerTrigger::Relative(5 * 1000 * 1000)) {
         warn!("Failed to set timer: {e}.");
         uefi::boot::close_event(timer).unwrap();
-        return (false, false);
-    };
-    let mut events = unsafe {
-        [
-            timer.unsafe_clone(),
-            uefi::system::with_stdin(|v| v.wait_for_key_event()).unwrap(),
-        ]
-    };
-    let i = match uefi::boot::wait_for_event(&mut events) {
-        Ok(v) => v,
-        Err(e) => {
-            warn!("Failed to wait for event: {e}.");
-            uefi::boot::close_event(timer).unwrap();
-            return (false, false);
-        }
+        return flags;
     };
+    let key_event = uefi::system::with_stdin(|v| v.wait_for_key_event()).unwrap();
 
-    uefi::boot::close_event(timer).unwrap();
-    if i == 0 {
-        return (false, false);
-    }
+    loop {
+        let mut events = unsafe { [timer.unsafe_clone(), key_event.unsafe_clone()] };
+        match uefi::boot::wait_for_event(&mut events) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(e) => {
+                warn!("Failed to wait for event: {e}.");
+                break;
+            }
+        }
 
-    uefi::system::with_stdin(|stdin| {
-        let mut verbose = false;
-        let mut serial_enabled = false;
-        while let Ok(v) = stdin.read_key() {
-            match v {
-                Some(Key::Printable(v)) if v == Char16::try_from('v').unwrap() => {
-                    verbose = true;
-                    break;
-                }
-                Some(Key::Printable(v)) if v == Char16::try_from('s').unwrap() => {
-                    serial_enabled = true;
-                    break;
+        uefi::system::with_stdin(|stdin| {
+            while let Ok(Some(key)) = stdin.read_key() {
+                let Key::Printable(v) = key else {
+                    continue;
+                };
+                match char::from(v) {
+                    'v' => flags.verbose = true,
+                    's' => flags.serial_enabled = true,
+                    'd' => flags.osdt_snapshot = true,
+                    'k' => flags.oom_kill = true,
+                    'b' => flags.benchmark = true,
+                    _ => {}
                 }
-                _ => {}
             }
-        }
-        (verbose, serial_enabled)
-    })
+        });
+    }
+
+    uefi::boot::close_event(timer).unwrap();
+    flags
 }
 
 pub fn get_rsdp() -> *const u8 {
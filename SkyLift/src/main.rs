@@ -28,7 +28,7 @@ extern "efiapi" fn efi_main(image: Handle, st: *const core::ffi::c_void) -> Stat
     let fb_info = helpers::fb::init();
     helpers::setup::setup();
 
-    let (verbose, serial_enabled) = helpers::setup::check_boot_flags();
+    let flags = helpers::setup::check_boot_flags();
 
     let (kernel_buf, fkcache_buf) = {
         let mut esp = uefi::fs::FileSystem::new(uefi::boot::get_image_file_system(image).unwrap());
@@ -40,8 +40,8 @@ extern "efiapi" fn efi_main(image: Handle, st: *const core::ffi::c_void) -> Stat
         )
     };
 
+    // The extension cache is left reclaimable, the kernel frees it once it has been parsed.
     let mut mem_mgr = helpers::mem::MemoryManager::new();
-    mem_mgr.allocate((fkcache_buf.as_ptr() as _, fkcache_buf.len() as _));
 
     let kernel_main = helpers::elf::parse(&mut mem_mgr, kernel_buf);
 
@@ -50,8 +50,11 @@ extern "efiapi" fn efi_main(image: Handle, st: *const core::ffi::c_void) -> Stat
     mem_mgr.allocate((stack.as_ptr() as _, stack.len() as _));
 
     let boot_info = Box::leak(Box::new(skyliftkit::BootInfo::new(
-        verbose,
-        serial_enabled,
+        flags.verbose,
+        flags.serial_enabled,
+        flags.osdt_snapshot,
+        flags.oom_kill,
+        flags.benchmark,
         fb_info.map(|v| helpers::phys_to_kern_ref(Box::leak(v))),
         helpers::setup::get_rsdp(),
         helpers::phys_to_kern_slice_ref(fkcache_buf),
@@ -61,13 +64,12 @@ extern "efiapi" fn efi_main(image: Handle, st: *const core::ffi::c_void) -> Stat
     let memory_map_entry_count = uefi::boot::memory_map(MemoryType::LOADER_DATA)
         .unwrap()
         .len()
+        + mem_mgr.range_count()
         + 8;
     let mut memory_map_entries = Vec::with_capacity(memory_map_entry_count);
 
     for v in unsafe { uefi::boot::exit_boot_services(None).entries() } {
-        if let Some(v) = mem_mgr.mem_type_from_desc(v) {
-            memory_map_entries.push(v);
-        }
+        mem_mgr.push_entries(v, &mut memory_map_entries);
     }
     boot_info.memory_map = helpers::phys_to_kern_slice_ref(memory_map_entries.leak());
 
@@ -0,0 +1,158 @@
+// Copyright (c) ChefKiss 2021-2025. Licensed under the Thou Shalt Not Profit License version 1.5. See LICENSE for details.
+
+#![deny(warnings, clippy::nursery, unused_extern_crates)]
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    process::ExitCode,
+};
+
+use skykit::{
+    osdtentry::OSDTENTRY_NAME_KEY,
+    osvalue::OSValue,
+    snapshot::{OSDTSnapshot, SNAPSHOT_BEGIN_MARKER, SNAPSHOT_END_MARKER},
+};
+
+type FlatTree = BTreeMap<String, BTreeMap<String, OSValue>>;
+
+fn extract(log: &str) -> Result<Vec<OSDTSnapshot>, String> {
+    let mut ret = vec![];
+    let mut current: Option<String> = None;
+    for (i, line) in log.lines().map(|v| v.trim_end_matches('\r')).enumerate() {
+        if line.ends_with(SNAPSHOT_BEGIN_MARKER) {
+            current = Some(String::new());
+        } else if line.ends_with(SNAPSHOT_END_MARKER) {
+            if let Some(data) = current.take() {
+                ret.push(
+                    serde_json::from_str(&data)
+                        .map_err(|e| format!("Malformed snapshot ending on line {}: {e}", i + 1))?,
+                );
+            }
+        } else if let Some(data) = current.as_mut() {
+            data.push_str(line);
+        }
+    }
+    Ok(ret)
+}
+
+fn load(path: &str) -> Result<OSDTSnapshot, String> {
+    let log = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {path}: {e}"))?;
+    extract(&log)
+        .map_err(|e| format!("{path}: {e}"))?
+        .pop()
+        .ok_or_else(|| format!("No OSDT snapshot found in {path}"))
+}
+
+fn flatten(snapshot: &OSDTSnapshot, parent: &str, out: &mut FlatTree) {
+    let name = match snapshot.properties.get(OSDTENTRY_NAME_KEY) {
+        Some(OSValue::String(v)) => v.as_str(),
+        _ => "Unnamed",
+    };
+    let mut path = format!("{parent}/{name}");
+    let mut i = 1;
+    while out.contains_key(&path) {
+        i += 1;
+        path = format!("{parent}/{name}#{i}");
+    }
+
+    out.insert(path.clone(), snapshot.properties.clone());
+    for child in &snapshot.children {
+        flatten(child, &path, out);
+    }
+}
+
+fn diff(old: &FlatTree, new: &FlatTree, ignored: &[String]) -> bool {
+    let mut differs = false;
+    let mut paths: BTreeSet<_> = old.keys().collect();
+    paths.extend(new.keys());
+    for path in paths {
+        let (old, new) = match (old.get(path), new.get(path)) {
+            (Some(_), None) => {
+                println!("- {path}");
+                differs = true;
+                continue;
+            }
+            (None, Some(_)) => {
+                println!("+ {path}");
+                differs = true;
+                continue;
+            }
+            (Some(old), Some(new)) => (old, new),
+            (None, None) => unreachable!(),
+        };
+
+        let mut keys: BTreeSet<_> = old.keys().collect();
+        keys.extend(new.keys());
+        for key in keys.into_iter().filter(|v| !ignored.contains(v)) {
+            match (old.get(key), new.get(key)) {
+                (Some(a), Some(b)) if a == b => continue,
+                (Some(a), Some(b)) => println!("~ {path} {key}: {} -> {}", a, b),
+                (Some(a), None) => {
+                    println!("- {path} {key}: {}", a)
+                }
+                (None, Some(b)) => {
+                    println!("+ {path} {key}: {}", b)
+                }
+                (None, None) => unreachable!(),
+            }
+            differs = true;
+        }
+    }
+    differs
+}
+
+fn usage() -> ExitCode {
+    eprintln!("Usage: OSDTSnapshot show <serial log>");
+    eprintln!("       OSDTSnapshot diff [-i <key>]... <old serial log> <new serial log>");
+    ExitCode::from(2)
+}
+
+fn run(args: &[String]) -> Result<ExitCode, String> {
+    match args.first().map(String::as_str) {
+        Some("show") if args.len() == 2 => {
+            let snapshot = load(&args[1])?;
+            println!(
+                "{}",
+                ron::ser::to_string_pretty(&snapshot, ron::ser::PrettyConfig::default())
+                    .map_err(|e| e.to_string())?
+            );
+            Ok(ExitCode::SUCCESS)
+        }
+        Some("diff") => {
+            let mut ignored = vec![];
+            let mut paths = vec![];
+            let mut args = args.iter().skip(1);
+            while let Some(arg) = args.next() {
+                if arg == "-i" {
+                    let Some(key) = args.next() else {
+                        return Ok(usage());
+                    };
+                    ignored.push(key.clone());
+                } else {
+                    paths.push(arg.as_str());
+                }
+            }
+            let [old, new] = paths[..] else {
+                return Ok(usage());
+            };
+
+            let (mut old_flat, mut new_flat) = (FlatTree::new(), FlatTree::new());
+            flatten(&load(old)?, "", &mut old_flat);
+            flatten(&load(new)?, "", &mut new_flat);
+            Ok(if diff(&old_flat, &new_flat, &ignored) {
+                ExitCode::FAILURE
+            } else {
+                ExitCode::SUCCESS
+            })
+        }
+        _ => Ok(usage()),
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    run(&args).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        ExitCode::from(2)
+    })
+}
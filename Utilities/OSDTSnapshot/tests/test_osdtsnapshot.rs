@@ -0,0 +1,150 @@
+// Copyright (c) ChefKiss 2021-2025. Licensed under the Thou Shalt Not Profit License version 1.5. See LICENSE for details.
+
+#![deny(warnings, clippy::nursery, unused_extern_crates)]
+
+use std::{collections::BTreeMap, path::PathBuf, process::Command};
+
+use skykit::{
+    osdtentry::OSDTENTRY_NAME_KEY,
+    osvalue::OSValue,
+    snapshot::{OSDTSnapshot, SNAPSHOT_BEGIN_MARKER, SNAPSHOT_END_MARKER},
+};
+
+fn entry(
+    id: u64,
+    name: &str,
+    props: &[(&str, OSValue)],
+    children: Vec<OSDTSnapshot>,
+) -> OSDTSnapshot {
+    let mut properties = BTreeMap::from([(OSDTENTRY_NAME_KEY.to_owned(), name.into())]);
+    properties.extend(props.iter().map(|(k, v)| ((*k).to_owned(), v.clone())));
+    OSDTSnapshot {
+        id,
+        properties,
+        children,
+    }
+}
+
+fn tree(ticks: u64, with_child: bool) -> OSDTSnapshot {
+    let children = if with_child {
+        vec![entry(2, "Child", &[("Index", 1u8.into())], vec![])]
+    } else {
+        vec![]
+    };
+    entry(
+        1,
+        "Root",
+        &[("Ticks", ticks.into()), ("Model", "Test".into())],
+        children,
+    )
+}
+
+/// Writes a serial log to a file of its own, the snapshot surrounded by unrelated output.
+fn write_log(name: &str, body: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("osdtsnapshot-{}-{name}", std::process::id()));
+    std::fs::write(
+        &path,
+        format!(
+            "[INFO] Booting\r\n[INFO] {SNAPSHOT_BEGIN_MARKER}\r\n{body}\r\n[INFO] \
+             {SNAPSHOT_END_MARKER}\r\n[INFO] Idle\r\n"
+        ),
+    )
+    .unwrap();
+    path
+}
+
+fn write_snapshot(name: &str, snapshot: &OSDTSnapshot) -> PathBuf {
+    write_log(name, &serde_json::to_string(snapshot).unwrap())
+}
+
+fn run(args: &[&str]) -> (i32, String, String) {
+    // The binary is renamed, so Cargo does not tell where it is, it sits next to `deps`.
+    let exe = std::env::current_exe().unwrap();
+    let out = Command::new(exe.parent().unwrap().parent().unwrap().join("OSDTSnapshot"))
+        .args(args)
+        .output()
+        .unwrap();
+    (
+        out.status.code().unwrap(),
+        String::from_utf8(out.stdout).unwrap(),
+        String::from_utf8(out.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn test_show() {
+    let path = write_snapshot("show", &tree(1, true));
+    let (code, out, _) = run(&["show", path.to_str().unwrap()]);
+    assert_eq!(code, 0);
+    let shown: OSDTSnapshot = ron::from_str(&out).unwrap();
+    assert_eq!(shown, tree(1, true));
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_show_last() {
+    let old = serde_json::to_string(&tree(1, false)).unwrap();
+    let new = serde_json::to_string(&tree(2, false)).unwrap();
+    let path = write_log(
+        "last",
+        &format!("{old}\r\n{SNAPSHOT_END_MARKER}\r\n{SNAPSHOT_BEGIN_MARKER}\r\n{new}"),
+    );
+    let (code, out, _) = run(&["show", path.to_str().unwrap()]);
+    assert_eq!(code, 0);
+    assert_eq!(ron::from_str::<OSDTSnapshot>(&out).unwrap(), tree(2, false));
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_diff() {
+    let paths = [
+        write_snapshot("diff-old", &tree(1, false)),
+        write_snapshot("diff-new", &tree(2, true)),
+    ];
+    let [old, new] = paths.each_ref().map(|v| v.to_str().unwrap());
+
+    assert_eq!(run(&["diff", old, old]), (0, String::new(), String::new()));
+
+    let (code, out, _) = run(&["diff", old, new]);
+    assert_eq!(code, 1);
+    assert_eq!(
+        out.lines().collect::<Vec<_>>(),
+        ["~ /Root Ticks: 1 -> 2", "+ /Root/Child"]
+    );
+
+    let (code, out, _) = run(&["diff", "-i", "Ticks", new, old]);
+    assert_eq!(code, 1);
+    assert_eq!(out.lines().collect::<Vec<_>>(), ["- /Root/Child"]);
+    for path in paths {
+        std::fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn test_errors() {
+    let malformed = write_log(
+        "malformed",
+        "{\"id\": 1, \"properties\": {\"Key\": {\"F64\": null}}}",
+    );
+    let (code, out, err) = run(&["show", malformed.to_str().unwrap()]);
+    assert_eq!((code, out.as_str()), (2, ""));
+    assert!(err.contains("Malformed snapshot ending on line 4"), "{err}");
+
+    let empty = std::env::temp_dir().join(format!("osdtsnapshot-{}-empty", std::process::id()));
+    std::fs::write(&empty, "[INFO] Booting\n").unwrap();
+    let (code, _, err) = run(&["diff", empty.to_str().unwrap(), malformed.to_str().unwrap()]);
+    assert_eq!(code, 2);
+    assert!(err.starts_with("No OSDT snapshot found in"), "{err}");
+
+    let (code, _, err) = run(&["show", "/nonexistent/serial.log"]);
+    assert_eq!(code, 2);
+    assert!(
+        err.starts_with("Failed to read /nonexistent/serial.log"),
+        "{err}"
+    );
+
+    assert_eq!(run(&["diff", "-i"]).0, 2);
+    assert_eq!(run(&["show"]).0, 2);
+    std::fs::remove_file(malformed).unwrap();
+    std::fs::remove_file(empty).unwrap();
+}
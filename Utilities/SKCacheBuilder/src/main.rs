@@ -15,6 +15,14 @@ fn main() {
                     ron::from_str(&std::fs::read_to_string(ent.path().join("Info.ron")).unwrap())
                         .unwrap();
                 println!("{}", info.identifier);
+                for (personality, matching) in &info.personalities {
+                    info.memory_limit(personality).unwrap();
+                    assert!(
+                        matching.values().all(skykit::osvalue::OSValue::is_finite),
+                        "Personality {personality} of {} has a non-finite value",
+                        info.identifier
+                    );
+                }
                 let payload = std::fs::read(PathBuf::from("../../target/Extensions").join(
                     format!("{}.exec", info.identifier.rsplit('.').next().unwrap()),
                 ))
@@ -22,6 +30,18 @@ fn main() {
                 (info, payload)
             })
             .collect(),
+        std::fs::read_dir("../../target/Libraries")
+            .into_iter()
+            .flatten()
+            .filter_map(Result::ok)
+            .filter(|v| v.path().extension().is_some_and(|v| v == "so"))
+            .map(|ent| {
+                (
+                    ent.file_name().into_string().unwrap(),
+                    std::fs::read(ent.path()).unwrap(),
+                )
+            })
+            .collect(),
     );
     std::fs::write(
         "../../Drive/System/SkyKitExtensions",